@@ -0,0 +1,106 @@
+use crate::computer::{ProgramState, StepResult};
+
+/// What an ASCII program printed before handing control back.  Values
+/// outside the ASCII range can't be text, so the last of them is reported as
+/// the `answer` instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsciiOutput {
+    pub text: String,
+    pub answer: Option<i64>,
+    pub result: StepResult,
+}
+
+/// Wraps a `ProgramState` that talks in lines of ASCII text.
+#[derive(Debug, Clone)]
+pub struct AsciiMachine {
+    pub state: ProgramState,
+}
+
+impl AsciiMachine {
+    pub fn new(program: &[i64]) -> AsciiMachine {
+        AsciiMachine {
+            state: ProgramState::new(program),
+        }
+    }
+
+    /// Queues one line of input, adding the newline.
+    pub fn send_line(&mut self, line: &str) {
+        self.state.push_inputs(line.bytes().map(|b| b as i64));
+        self.state.push_input(10);
+    }
+
+    pub fn send_lines<'a, I: IntoIterator<Item = &'a str>>(
+        &mut self,
+        lines: I,
+    ) {
+        for line in lines {
+            self.send_line(line);
+        }
+    }
+
+    /// Runs until the program halts or wants more input than is queued.
+    pub fn run(&mut self) -> AsciiOutput {
+        let result = self.state.run_until_blocked();
+        let mut text = String::new();
+        let mut answer = None;
+        for x in self.state.drain_output() {
+            if (0..128).contains(&x) {
+                text.push(x as u8 as char);
+            } else {
+                answer = Some(x);
+            }
+        }
+        AsciiOutput {
+            text,
+            answer,
+            result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_ascii() {
+        // Echoes a line back, then prints the sum of its characters
+        let program = assemble(
+            "
+            loop:
+                in -> [c]
+                out [c]
+                add [sum], [c] -> [sum]
+                eq [c], #10 -> [t]
+                jf [t], #loop
+                out [sum]
+                hlt
+            c: .data 0
+            sum: .data 0
+            t: .data 0
+            ",
+        )
+        .unwrap();
+
+        let mut machine = AsciiMachine::new(&program);
+        assert_eq!(
+            machine.run(),
+            AsciiOutput {
+                text: String::new(),
+                answer: None,
+                result: StepResult::NeedInput,
+            }
+        );
+
+        machine.send_lines(vec!["Hi"]);
+        assert_eq!(
+            machine.run(),
+            AsciiOutput {
+                text: "Hi\n".to_string(),
+                answer: Some(72 + 105 + 10),
+                result: StepResult::Halted,
+            }
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::computer::{Instruction, Mode, Opcode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Statement {
+    Instruction(Opcode, Vec<(Mode, Value)>),
+    Data(Vec<Value>),
+}
+
+fn error(line: usize, message: String) -> AsmError {
+    AsmError { line, message }
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_value(text: &str, line: usize) -> Result<Value, AsmError> {
+    let text = text.trim();
+    if let Ok(x) = text.parse::<i64>() {
+        Ok(Value::Number(x))
+    } else if is_label(text) {
+        Ok(Value::Label(text.to_string()))
+    } else {
+        Err(error(
+            line,
+            format!("Expected a number or label, got {:?}", text),
+        ))
+    }
+}
+
+// #value, [value], [rb], [rb+n] or [rb-n]
+fn parse_operand(text: &str, line: usize) -> Result<(Mode, Value), AsmError> {
+    let text = text.trim();
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok((Mode::Immediate, parse_value(value, line)?));
+    }
+    if let Some(inner) =
+        text.strip_prefix('[').and_then(|t| t.strip_suffix(']'))
+    {
+        let inner = inner.trim();
+        // `rb` alone or followed by a sign; anything else is a label like
+        // `rbuf` that just happens to start with it
+        let relative = inner
+            .strip_prefix("rb")
+            .map(|offset| offset.trim())
+            .filter(|offset| {
+                offset.is_empty()
+                    || offset.starts_with('+')
+                    || offset.starts_with('-')
+            });
+        if let Some(offset) = relative {
+            let offset = offset.replace(' ', "");
+            let offset = offset.strip_prefix('+').unwrap_or(&offset);
+            if offset.is_empty() {
+                return Ok((Mode::Relative, Value::Number(0)));
+            }
+            return Ok((Mode::Relative, parse_value(offset, line)?));
+        }
+        return Ok((Mode::Position, parse_value(inner, line)?));
+    }
+    Err(error(line, format!("Bad operand {:?}", text)))
+}
+
+fn parse_statement(text: &str, line: usize) -> Result<Statement, AsmError> {
+    let (head, rest) = match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim()),
+        None => (text, ""),
+    };
+
+    if head == ".data" {
+        let values = rest
+            .split(',')
+            .map(|value| parse_value(value, line))
+            .collect::<Result<_, _>>()?;
+        return Ok(Statement::Data(values));
+    }
+
+    let opcode = Opcode::from_mnemonic(head)
+        .ok_or_else(|| error(line, format!("Unknown mnemonic {:?}", head)))?;
+
+    // The destination may be split off with `->` or given as the last operand
+    let operands: Vec<&str> = rest
+        .split("->")
+        .flat_map(|part| part.split(','))
+        .map(|operand| operand.trim())
+        .filter(|operand| !operand.is_empty())
+        .collect();
+    if operands.len() != opcode.n_params() {
+        return Err(error(
+            line,
+            format!(
+                "{} takes {} operands, got {}",
+                opcode.mnemonic(),
+                opcode.n_params(),
+                operands.len()
+            ),
+        ));
+    }
+
+    let operands = operands
+        .into_iter()
+        .map(|operand| parse_operand(operand, line))
+        .collect::<Result<Vec<_>, _>>()?;
+    if opcode.writes() && operands.last().unwrap().0 == Mode::Immediate {
+        return Err(error(line, "Cannot write to an immediate".to_string()));
+    }
+    Ok(Statement::Instruction(opcode, operands))
+}
+
+fn resolve(
+    value: &Value,
+    labels: &HashMap<String, usize>,
+    line: usize,
+) -> Result<i64, AsmError> {
+    match value {
+        Value::Number(x) => Ok(*x),
+        Value::Label(name) => match labels.get(name) {
+            Some(address) => Ok(*address as i64),
+            None => Err(error(line, format!("Undefined label {:?}", name))),
+        },
+    }
+}
+
+/// Assembles Intcode from a small mnemonic language, one statement a line:
+///
+/// ```text
+/// loop:                       ; labels end with a colon
+///     IN -> [rb+1]            ; in/out/add/mul/lt/eq/jt/jf/arb/hlt
+///     ADD [rb+1], #-3 -> [x]  ; #immediate, [position], [rb+relative]
+///     JT #1, #loop            ; labels work anywhere a number does
+/// x:  .data 0                 ; raw values
+/// ```
+///
+/// The output of `disasm::listing` assembles back to the original program.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = vec![];
+
+    // First pass parses everything and lays out label addresses
+    let mut address = 0;
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let mut text = text.split(';').next().unwrap().trim();
+
+        while let Some(colon) = text.find(':') {
+            let name = text[..colon].trim();
+            if !is_label(name) {
+                return Err(error(line, format!("Bad label {:?}", name)));
+            }
+            if labels.insert(name.to_string(), address).is_some() {
+                return Err(error(line, format!("Duplicate label {:?}", name)));
+            }
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(text, line)?;
+        address += match &statement {
+            Statement::Instruction(opcode, _) => opcode.n_params() + 1,
+            Statement::Data(values) => values.len(),
+        };
+        statements.push((line, statement));
+    }
+
+    // Second pass emits code with every label resolved
+    let mut program = Vec::with_capacity(address);
+    for (line, statement) in statements {
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let mut modes = [Mode::Position; 3];
+                for (mode, (operand_mode, _)) in modes.iter_mut().zip(&operands)
+                {
+                    *mode = *operand_mode;
+                }
+                program.push(Instruction { opcode, modes }.encode());
+                for (_, value) in operands {
+                    program.push(resolve(&value, &labels, line)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    program.push(resolve(&value, &labels, line)?);
+                }
+            }
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::computer::{load_program, ProgramState, StepResult};
+    use crate::disasm::listing;
+
+    #[test]
+    fn test_assemble() {
+        // Day 5's "is the input equal to 8" example
+        let source = "
+            in -> [value]
+            EQ [value], [eight] -> [value]   ; lower or upper case
+            out [value]
+            hlt
+            value: .data -1
+            eight: .data 8
+        ";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8])
+        );
+
+        let source = "
+            start: arb #-2
+            add [rb], #1, [rb-1]
+                jt #1, #start ; loop forever
+        ";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![109, -2, 21201, 0, 1, -1, 1105, 1, 0])
+        );
+
+        // Labels starting with `rb` are still position mode
+        let source = "
+            out [rbuf]
+            out [rb + 1]
+            hlt
+            rbuf: .data 5
+        ";
+        assert_eq!(assemble(source), Ok(vec![4, 5, 204, 1, 99, 5]));
+
+        let source = "in -> [rb+10]\nmul [rb+10], #3 -> [20]\nout [20]\nhlt";
+        let mut state = ProgramState::new(&assemble(source).unwrap());
+        state.push_input(14);
+        assert_eq!(state.run_until_blocked(), StepResult::Halted);
+        assert_eq!(state.drain_output(), vec![42]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let line = |source: &str| assemble(source).unwrap_err().line;
+        assert_eq!(line("hlt\nnop"), 2);
+        assert_eq!(line("add #1, #2"), 1);
+        assert_eq!(line("add #1, #2 -> #3"), 1);
+        assert_eq!(line("out [nowhere]"), 1);
+        assert_eq!(line("a: hlt\na: hlt"), 2);
+        assert_eq!(line("out 5"), 1);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for name in &["05", "07", "09", "11", "13", "15", "17", "19"] {
+            let program = load_program(&format!("inputs/{}.txt", name));
+            assert_eq!(assemble(&listing(&program)), Ok(program));
+        }
+    }
+}
@@ -1,245 +1,885 @@
-extern crate regex;
-use regex::Regex;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
 
+use crate::memory::Memory;
+use crate::profile::Profile;
+use crate::trace::{Trace, TraceEntry};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntcodeError {
+    UnknownOpcode {
+        ic: usize,
+        opcode: i64,
+    },
+    BadMode {
+        ic: usize,
+        opcode: i64,
+        mode: u32,
+    },
+    NegativeAddress {
+        ic: usize,
+        opcode: i64,
+        address: i64,
+    },
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::UnknownOpcode { ic, opcode } => {
+                write!(f, "Unknown opcode {} at ic {}", opcode, ic)
+            }
+            IntcodeError::BadMode { ic, opcode, mode } => write!(
+                f,
+                "Unrecognized mode {} in opcode {} at ic {}",
+                mode, opcode, ic
+            ),
+            IntcodeError::NegativeAddress {
+                ic,
+                opcode,
+                address,
+            } => write!(
+                f,
+                "Cannot dereference negative address {} in opcode {} at ic {}",
+                address, opcode, ic
+            ),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ProgramStatus {
     Running,
+    WaitingForInput,
+    Halted,
+}
+
+/// Why a call to `run` handed control back to the caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepResult {
+    Output(i64),
+    NeedInput,
     Halted,
+    /// `cycles` reached `budget` before the instruction at `ic` could run.
+    /// Raising the budget and running again carries on from there.
+    BudgetExceeded,
 }
 
+/// Complete state of one Intcode machine.  Cloning is cheap since memory is
+/// copy-on-write, so search drivers can fork a machine at a branch point and
+/// run each copy independently.
+#[derive(Debug, Clone)]
 pub struct ProgramState {
-    pub memory: Vec<i64>,
+    pub memory: Memory,
     pub ic: usize,
     pub relative_base: i64,
     pub status: ProgramStatus,
+    /// Instructions executed so far, across every call to `run`.
+    pub cycles: u64,
+    /// Once `cycles` reaches this, running stops with
+    /// `StepResult::BudgetExceeded` instead of executing anything more.
+    pub budget: Option<u64>,
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+    /// Every executed instruction is appended here while this is `Some`.
+    pub trace: Option<Trace>,
+    /// Execution counts are gathered here while this is `Some`.
+    pub profile: Option<Profile>,
 }
 
 impl ProgramState {
-    pub fn new(memory: &Vec<i64>) -> ProgramState {
-        let mut copy = memory.clone();
-        copy.extend_from_slice(&vec![0; 10000].as_slice());
+    pub fn new(program: &[i64]) -> ProgramState {
         ProgramState {
-            memory: copy,
+            memory: Memory::new(program),
             ic: 0,
             relative_base: 0,
             status: ProgramStatus::Running,
+            cycles: 0,
+            budget: None,
+            input: VecDeque::new(),
+            output: vec![],
+            trace: None,
+            profile: None,
         }
     }
 
-    pub fn write(&mut self, value: i64, position: usize) {
-        if position > self.memory.len() {
-            let needs = position - self.memory.len() + 1;
-            self.memory.extend_from_slice(&vec![0; needs].as_slice());
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    pub fn push_inputs<I: IntoIterator<Item = i64>>(&mut self, values: I) {
+        self.input.extend(values);
+    }
+
+    pub fn drain_output(&mut self) -> Vec<i64> {
+        self.output.drain(..).collect()
+    }
+
+    /// Runs until the program halts or runs out of queued input, collecting
+    /// every output in the output buffer along the way.
+    pub fn run_until_blocked(&mut self) -> StepResult {
+        self.try_run_until_blocked()
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_run_until_blocked(
+        &mut self,
+    ) -> Result<StepResult, IntcodeError> {
+        loop {
+            match try_run(self)? {
+                StepResult::Output(x) => self.output.push(x),
+                result => return Ok(result),
+            }
         }
+    }
+
+    pub fn write(&mut self, value: i64, position: usize) {
         self.memory[position] = value;
     }
-}
 
-pub fn param_parse(param: i64, mode: u32, state: &ProgramState) -> i64 {
-    let output = {
-        match mode {
-            0 => {
-                if param < 0 {
-                    panic!("Cannot dereference negative address: {}", param);
-                } else {
-                    state.memory[param as usize]
-                }
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut f = File::create(path)?;
+        f.write_all(self.to_snapshot().as_bytes())
+    }
+
+    pub fn load_snapshot<P: AsRef<Path>>(path: P) -> io::Result<ProgramState> {
+        let mut f = File::open(path)?;
+        let mut buffer = String::new();
+        f.read_to_string(&mut buffer)?;
+        ProgramState::from_snapshot(&buffer)
+    }
+
+    /// Serializes the whole machine as text, one `key value` pair per line.
+    /// Memory is written as `memory <address> <values>` runs with trailing
+    /// zeros dropped.
+    pub fn to_snapshot(&self) -> String {
+        let mut lines = vec![
+            format!("ic {}", self.ic),
+            format!("relative_base {}", self.relative_base),
+            format!("status {:?}", self.status),
+            format!("cycles {}", self.cycles),
+            format!("input {}", join(self.input.iter())),
+            format!("output {}", join(self.output.iter())),
+        ];
+        for (address, page) in self.memory.pages() {
+            let len = page.iter().rposition(|x| *x != 0).map_or(0, |i| i + 1);
+            if len > 0 {
+                lines.push(format!(
+                    "memory {} {}",
+                    address,
+                    join(&page[..len])
+                ));
             }
-            1 => param,
-            2 => {
-                if (param + state.relative_base) < 0 {
-                    panic!(
-                        "Cannot dereference negative address: {}",
-                        param + state.relative_base
-                    );
-                } else {
-                    state.memory[(param + state.relative_base) as usize]
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn from_snapshot(snapshot: &str) -> io::Result<ProgramState> {
+        let mut state = ProgramState::new(&[]);
+        for line in snapshot.lines() {
+            let mut fields = line.split_whitespace();
+            let key = fields.next().unwrap_or("");
+            let value = fields.next().unwrap_or("");
+            match key {
+                "" => continue,
+                "ic" => state.ic = parse_field(value)?,
+                "relative_base" => state.relative_base = parse_field(value)?,
+                "cycles" => state.cycles = parse_field(value)?,
+                "status" => {
+                    state.status = match value {
+                        "Running" => ProgramStatus::Running,
+                        "WaitingForInput" => ProgramStatus::WaitingForInput,
+                        "Halted" => ProgramStatus::Halted,
+                        _ => return Err(bad_snapshot(line)),
+                    }
+                }
+                "input" => state.input = parse_list(value)?.into(),
+                "output" => state.output = parse_list(value)?,
+                "memory" => {
+                    let address: usize = parse_field(value)?;
+                    let values = parse_list(fields.next().unwrap_or(""))?;
+                    for (i, x) in values.into_iter().enumerate() {
+                        state.memory[address + i] = x;
+                    }
                 }
+                _ => return Err(bad_snapshot(line)),
             }
-            _ => panic!("Unrecognized mode"),
         }
-    };
-    output
+        Ok(state)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustBase,
+    Halt,
 }
 
-pub fn addr_parse(param: i64, mode: u32, state: &ProgramState) -> usize {
-    let output = {
-        match mode {
-            0 => param as usize,
-            2 => (param + state.relative_base) as usize,
-            _ => panic!("Unrecognized mode"),
+impl Opcode {
+    pub fn from_code(code: i64) -> Option<Opcode> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Multiply),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpIfTrue),
+            6 => Some(Opcode::JumpIfFalse),
+            7 => Some(Opcode::LessThan),
+            8 => Some(Opcode::Equals),
+            9 => Some(Opcode::AdjustBase),
+            99 => Some(Opcode::Halt),
+            _ => None,
         }
-    };
+    }
+
+    pub fn code(self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equals => 8,
+            Opcode::AdjustBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "ADD",
+            Opcode::Multiply => "MUL",
+            Opcode::Input => "IN",
+            Opcode::Output => "OUT",
+            Opcode::JumpIfTrue => "JT",
+            Opcode::JumpIfFalse => "JF",
+            Opcode::LessThan => "LT",
+            Opcode::Equals => "EQ",
+            Opcode::AdjustBase => "ARB",
+            Opcode::Halt => "HLT",
+        }
+    }
+
+    pub fn from_mnemonic(name: &str) -> Option<Opcode> {
+        match name.to_uppercase().as_str() {
+            "ADD" => Some(Opcode::Add),
+            "MUL" => Some(Opcode::Multiply),
+            "IN" => Some(Opcode::Input),
+            "OUT" => Some(Opcode::Output),
+            "JT" => Some(Opcode::JumpIfTrue),
+            "JF" => Some(Opcode::JumpIfFalse),
+            "LT" => Some(Opcode::LessThan),
+            "EQ" => Some(Opcode::Equals),
+            "ARB" => Some(Opcode::AdjustBase),
+            "HLT" => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    /// Whether the last parameter is an address the instruction writes to.
+    pub fn writes(self) -> bool {
+        matches!(
+            self,
+            Opcode::Add
+                | Opcode::Multiply
+                | Opcode::Input
+                | Opcode::LessThan
+                | Opcode::Equals
+        )
+    }
+
+    pub fn n_params(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply => 3,
+            Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    pub fn from_digit(digit: i64) -> Option<Mode> {
+        match digit {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn digit(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    pub fn len(&self) -> usize {
+        self.opcode.n_params() + 1
+    }
+
+    /// Inverse of `decode`, with no mode digits past the last parameter.
+    pub fn encode(&self) -> i64 {
+        let modes = self.modes[..self.opcode.n_params()].iter().rev();
+        let digits = modes.fold(0, |acc, mode| acc * 10 + mode.digit());
+        digits * 100 + self.opcode.code()
+    }
+}
+
+/// Splits a raw opcode into its operation and parameter modes using plain
+/// integer arithmetic.  Mode digits past the opcode's last parameter are
+/// ignored, same as leading zeros.
+pub fn decode(raw: i64, ic: usize) -> Result<Instruction, IntcodeError> {
+    let unknown = IntcodeError::UnknownOpcode { ic, opcode: raw };
+    if raw < 0 {
+        return Err(unknown);
+    }
+    let opcode = Opcode::from_code(raw % 100).ok_or(unknown)?;
+
+    let mut modes = [Mode::Position; 3];
+    let mut digits = raw / 100;
+    for mode in modes.iter_mut().take(opcode.n_params()) {
+        *mode = Mode::from_digit(digits % 10).ok_or(IntcodeError::BadMode {
+            ic,
+            opcode: raw,
+            mode: (digits % 10) as u32,
+        })?;
+        digits /= 10;
+    }
+
+    Ok(Instruction { opcode, modes })
+}
+
+pub fn load_program(name: &str) -> Vec<i64> {
+    let mut f = File::open(name).unwrap();
+    let mut buffer = String::new();
+    f.read_to_string(&mut buffer).unwrap();
+    let mut output = Vec::new();
+    for el in buffer.split(',') {
+        if let Ok(x) = el.trim().parse::<i64>() {
+            output.push(x)
+        }
+    }
     output
 }
 
-pub fn run(input: i64, state: &mut ProgramState) -> Option<i64> {
-    let re = Regex::new(r"^(\d*?)(\d{1,2})$").unwrap();
+fn join<'a, I: IntoIterator<Item = &'a i64>>(values: I) -> String {
+    values
+        .into_iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
 
-    let mut input_consumed = false;
+fn bad_snapshot(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Bad snapshot line: {:?}", line),
+    )
+}
 
-    loop {
-        let opcode = state.memory[state.ic];
-        // Parse opcode for mode using regex
-        let opstr = opcode.to_string();
-        let cap = re.captures(&opstr[..]).unwrap();
-        let opcode = cap[cap.len() - 1].parse::<i64>().unwrap();
-        let mut modes: Vec<u32> = cap[1]
-            .chars()
-            .map(|c| c.to_digit(10).unwrap())
-            .rev()
-            .collect();
-
-        match opcode {
-            1 => {
-                // Add
-                let n_params = 3;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let params =
-                    &state.memory[state.ic + 1..state.ic + 1 + n_params];
-
-                let a = param_parse(params[0], modes[0], state);
-                let b = param_parse(params[1], modes[1], state);
-                let addr = addr_parse(params[2], modes[2], state);
-
-                state.write(a + b, addr);
-                state.ic += n_params + 1;
-            }
-            2 => {
-                // Multiply
-                let n_params = 3;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let params =
-                    &state.memory[state.ic + 1..state.ic + 1 + n_params];
-
-                let a = param_parse(params[0], modes[0], state);
-                let b = param_parse(params[1], modes[1], state);
-                let addr = addr_parse(params[2], modes[2], state);
-
-                state.write(a * b, addr);
-                state.ic += n_params + 1;
-            }
-            3 => {
-                // Store input in memory
-                if !input_consumed {
-                    let n_params = 1;
-                    let implicit_zeros = n_params - modes.len();
-                    modes
-                        .extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                    let param = state.memory[state.ic + 1];
-
-                    let addr = addr_parse(param, modes[0], state);
-
-                    state.write(input, addr);
-                    state.ic += n_params + 1;
-                    input_consumed = true;
-                } else {
-                    return None;
-                }
-            }
-            4 => {
-                // Load output from memory, return output
-                let n_params = 1;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let param = state.memory[state.ic + 1];
+fn parse_field<T: std::str::FromStr>(field: &str) -> io::Result<T> {
+    field.parse().map_err(|_| bad_snapshot(field))
+}
 
-                let output = param_parse(param, modes[0], state);
-                state.ic += n_params + 1;
+fn parse_list(field: &str) -> io::Result<Vec<i64>> {
+    field
+        .split(',')
+        .filter(|el| !el.is_empty())
+        .map(parse_field)
+        .collect()
+}
 
-                return Some(output);
-            }
-            5 => {
-                // Jump if true
-                let n_params = 2;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let params =
-                    &state.memory[state.ic + 1..state.ic + 1 + n_params];
-
-                let p1 = param_parse(params[0], modes[0], state);
-                let p2 = param_parse(params[1], modes[1], state);
-
-                if p1 != 0 {
-                    state.ic = p2 as usize;
-                } else {
-                    state.ic += n_params + 1;
-                }
-            }
-            6 => {
-                // Jump if false
-                let n_params = 2;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let params =
-                    &state.memory[state.ic + 1..state.ic + 1 + n_params];
-
-                let p1 = param_parse(params[0], modes[0], state);
-                let p2 = param_parse(params[1], modes[1], state);
-
-                if p1 == 0 {
-                    state.ic = p2 as usize;
-                } else {
-                    state.ic += n_params + 1;
-                }
-            }
-            7 => {
-                // Less than
-                let n_params = 3;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let params =
-                    &state.memory[state.ic + 1..state.ic + 1 + n_params];
-
-                let p1 = param_parse(params[0], modes[0], state);
-                let p2 = param_parse(params[1], modes[1], state);
-                let addr = addr_parse(params[2], modes[2], state);
-
-                if p1 < p2 {
-                    state.memory[addr] = 1;
-                } else {
-                    state.memory[addr] = 0;
-                }
-                state.ic += n_params + 1;
+fn negative_address(address: i64, state: &ProgramState) -> IntcodeError {
+    IntcodeError::NegativeAddress {
+        ic: state.ic,
+        opcode: state.memory[state.ic],
+        address,
+    }
+}
+
+pub fn param_parse(
+    param: i64,
+    mode: Mode,
+    state: &ProgramState,
+) -> Result<i64, IntcodeError> {
+    match mode {
+        Mode::Position => {
+            if param < 0 {
+                Err(negative_address(param, state))
+            } else {
+                Ok(state.memory[param as usize])
             }
-            8 => {
-                // Equals
-                let n_params = 3;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let params =
-                    &state.memory[state.ic + 1..state.ic + 1 + n_params];
-
-                let p1 = param_parse(params[0], modes[0], state);
-                let p2 = param_parse(params[1], modes[1], state);
-                let addr = addr_parse(params[2], modes[2], state);
-
-                if p1 == p2 {
-                    state.memory[addr] = 1;
-                } else {
-                    state.memory[addr] = 0;
-                }
-                state.ic += n_params + 1;
+        }
+        Mode::Immediate => Ok(param),
+        Mode::Relative => {
+            let address = param + state.relative_base;
+            if address < 0 {
+                Err(negative_address(address, state))
+            } else {
+                Ok(state.memory[address as usize])
             }
-            9 => {
-                // Relative base offset
-                let n_params = 1;
-                let implicit_zeros = n_params - modes.len();
-                modes.extend_from_slice(&vec![0; implicit_zeros].as_slice());
-                let param = state.memory[state.ic + 1];
+        }
+    }
+}
+
+pub fn addr_parse(
+    param: i64,
+    mode: Mode,
+    state: &ProgramState,
+) -> Result<usize, IntcodeError> {
+    let address = match mode {
+        Mode::Position => param,
+        Mode::Relative => param + state.relative_base,
+        Mode::Immediate => {
+            return Err(IntcodeError::BadMode {
+                ic: state.ic,
+                opcode: state.memory[state.ic],
+                mode: 1,
+            })
+        }
+    };
+    if address < 0 {
+        Err(negative_address(address, state))
+    } else {
+        Ok(address as usize)
+    }
+}
+
+fn jump_target(
+    target: i64,
+    state: &ProgramState,
+) -> Result<usize, IntcodeError> {
+    if target < 0 {
+        Err(negative_address(target, state))
+    } else {
+        Ok(target as usize)
+    }
+}
+
+// Value of the n-th parameter of the instruction at ic
+fn operand(
+    inst: &Instruction,
+    n: usize,
+    state: &ProgramState,
+) -> Result<i64, IntcodeError> {
+    param_parse(state.memory[state.ic + 1 + n], inst.modes[n], state)
+}
+
+// Address the n-th parameter of the instruction at ic reads from, if it
+// reads memory at all
+fn source_address(
+    inst: &Instruction,
+    n: usize,
+    state: &ProgramState,
+) -> Option<usize> {
+    let param = state.memory[state.ic + 1 + n];
+    let address = match inst.modes[n] {
+        Mode::Position => param,
+        Mode::Relative => param + state.relative_base,
+        Mode::Immediate => return None,
+    };
+    if address < 0 {
+        None
+    } else {
+        Some(address as usize)
+    }
+}
+
+// Address the n-th parameter of the instruction at ic writes to
+fn destination(
+    inst: &Instruction,
+    n: usize,
+    state: &ProgramState,
+) -> Result<usize, IntcodeError> {
+    addr_parse(state.memory[state.ic + 1 + n], inst.modes[n], state)
+}
+
+pub fn run(state: &mut ProgramState) -> StepResult {
+    try_run(state).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_run(state: &mut ProgramState) -> Result<StepResult, IntcodeError> {
+    state.status = ProgramStatus::Running;
+    if let Some(profile) = &mut state.profile {
+        profile.runs.push(0);
+    }
+    loop {
+        if let Some(result) = step(state)? {
+            return Ok(result);
+        }
+    }
+}
+
+/// Executes the single instruction at `ic`.  Returns the reason to hand
+/// control back to the caller, if there is one.
+pub fn step(
+    state: &mut ProgramState,
+) -> Result<Option<StepResult>, IntcodeError> {
+    if let Some(budget) = state.budget {
+        if state.cycles >= budget {
+            return Ok(Some(StepResult::BudgetExceeded));
+        }
+    }
+    let inst = decode(state.memory[state.ic], state.ic)?;
+    let result = if state.trace.is_none() && state.profile.is_none() {
+        execute(&inst, state)?
+    } else {
+        observe(&inst, state)?
+    };
+
+    // An input instruction that found nothing queued hasn't run yet
+    if result != Some(StepResult::NeedInput) {
+        state.cycles += 1;
+    }
+    Ok(result)
+}
+
+// Executes an instruction while feeding the tracer and profiler
+fn observe(
+    inst: &Instruction,
+    state: &mut ProgramState,
+) -> Result<Option<StepResult>, IntcodeError> {
+    // Resolve everything up front, since executing may overwrite operands
+    let ic = state.ic;
+    let n = inst.opcode.n_params();
+    let n_sources = n - inst.opcode.writes() as usize;
+    let reads: Vec<usize> = (0..n_sources)
+        .filter_map(|i| source_address(inst, i, state))
+        .collect();
+    let entry = match state.trace {
+        Some(_) => Some(TraceEntry {
+            ic,
+            opcode: inst.opcode,
+            operands: (0..n_sources)
+                .map(|i| operand(inst, i, state))
+                .collect::<Result<_, _>>()?,
+            write: None,
+            relative_base: None,
+        }),
+        None => None,
+    };
+    let target = if inst.opcode.writes() {
+        Some(destination(inst, n - 1, state)?)
+    } else {
+        None
+    };
+    let relative_base = state.relative_base;
 
-                let p1 = param_parse(param, modes[0], state);
+    let result = execute(inst, state)?;
+    if result == Some(StepResult::NeedInput) {
+        // Nothing ran, the input instruction is retried later
+        return Ok(result);
+    }
+    if let Some(profile) = &mut state.profile {
+        profile.record(ic, inst, &reads, target);
+    }
+    if let Some(mut entry) = entry {
+        entry.write = target.map(|address| (address, state.memory[address]));
+        if state.relative_base != relative_base {
+            entry.relative_base = Some(state.relative_base);
+        }
+        if let Some(trace) = &mut state.trace {
+            trace.entries.push(entry);
+        }
+    }
+    Ok(result)
+}
 
-                state.relative_base += p1;
-                state.ic += n_params + 1;
+fn execute(
+    inst: &Instruction,
+    state: &mut ProgramState,
+) -> Result<Option<StepResult>, IntcodeError> {
+    match inst.opcode {
+        Opcode::Add => {
+            let a = operand(inst, 0, state)?;
+            let b = operand(inst, 1, state)?;
+            let addr = destination(inst, 2, state)?;
+            state.write(a + b, addr);
+        }
+        Opcode::Multiply => {
+            let a = operand(inst, 0, state)?;
+            let b = operand(inst, 1, state)?;
+            let addr = destination(inst, 2, state)?;
+            state.write(a * b, addr);
+        }
+        Opcode::Input => {
+            // Store input in memory, or wait until some is queued
+            let addr = destination(inst, 0, state)?;
+            if let Some(input) = state.input.pop_front() {
+                state.write(input, addr);
+            } else {
+                state.status = ProgramStatus::WaitingForInput;
+                return Ok(Some(StepResult::NeedInput));
+            }
+        }
+        Opcode::Output => {
+            let output = operand(inst, 0, state)?;
+            state.ic += inst.len();
+            return Ok(Some(StepResult::Output(output)));
+        }
+        Opcode::JumpIfTrue => {
+            if operand(inst, 0, state)? != 0 {
+                state.ic = jump_target(operand(inst, 1, state)?, state)?;
+                return Ok(None);
             }
-            99 => {
-                // Halt the program
-                state.status = ProgramStatus::Halted;
-                return None;
+        }
+        Opcode::JumpIfFalse => {
+            if operand(inst, 0, state)? == 0 {
+                state.ic = jump_target(operand(inst, 1, state)?, state)?;
+                return Ok(None);
             }
-            _ => panic!("Unknown opcode: {:?}", opcode),
         }
+        Opcode::LessThan => {
+            let a = operand(inst, 0, state)?;
+            let b = operand(inst, 1, state)?;
+            let addr = destination(inst, 2, state)?;
+            state.write((a < b) as i64, addr);
+        }
+        Opcode::Equals => {
+            let a = operand(inst, 0, state)?;
+            let b = operand(inst, 1, state)?;
+            let addr = destination(inst, 2, state)?;
+            state.write((a == b) as i64, addr);
+        }
+        Opcode::AdjustBase => {
+            state.relative_base += operand(inst, 0, state)?;
+        }
+        Opcode::Halt => {
+            state.status = ProgramStatus::Halted;
+            return Ok(Some(StepResult::Halted));
+        }
+    }
+
+    state.ic += inst.len();
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_errors() {
+        let mut state = ProgramState::new(&[1, 0, 0, 0, 42]);
+        state.ic = 4;
+        assert_eq!(
+            try_run(&mut state),
+            Err(IntcodeError::UnknownOpcode { ic: 4, opcode: 42 })
+        );
+
+        let mut state = ProgramState::new(&[304, 0, 99]);
+        assert_eq!(
+            try_run(&mut state),
+            Err(IntcodeError::BadMode {
+                ic: 0,
+                opcode: 304,
+                mode: 3
+            })
+        );
+
+        let mut state = ProgramState::new(&[4, -7, 99]);
+        assert_eq!(
+            try_run(&mut state),
+            Err(IntcodeError::NegativeAddress {
+                ic: 0,
+                opcode: 4,
+                address: -7
+            })
+        );
+
+        let mut state = ProgramState::new(&[109, -3, 203, 1, 99]);
+        state.push_input(0);
+        assert_eq!(
+            try_run(&mut state),
+            Err(IntcodeError::NegativeAddress {
+                ic: 2,
+                opcode: 203,
+                address: -2
+            })
+        );
+
+        let mut state = ProgramState::new(&[1105, 1, -1]);
+        assert_eq!(
+            try_run(&mut state),
+            Err(IntcodeError::NegativeAddress {
+                ic: 0,
+                opcode: 1105,
+                address: -1
+            })
+        );
+    }
+
+    #[test]
+    fn test_step_results() {
+        // Reads two inputs and outputs their sum
+        let input = vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99];
+        let mut state = ProgramState::new(&input);
+        state.push_input(2);
+        assert_eq!(run(&mut state), StepResult::NeedInput);
+        assert_eq!(state.status, ProgramStatus::WaitingForInput);
+        state.push_input(3);
+        assert_eq!(run(&mut state), StepResult::Output(5));
+        assert_eq!(state.status, ProgramStatus::Running);
+        assert_eq!(run(&mut state), StepResult::Halted);
+        assert_eq!(state.status, ProgramStatus::Halted);
+    }
+
+    #[test]
+    fn test_run_until_blocked() {
+        // Echoes every input back out, forever
+        let input = vec![3, 100, 4, 100, 1105, 1, 0];
+        let mut state = ProgramState::new(&input);
+        state.push_inputs(vec![7, 8, 9]);
+        assert_eq!(state.run_until_blocked(), StepResult::NeedInput);
+        assert_eq!(state.drain_output(), vec![7, 8, 9]);
+        assert!(state.output.is_empty());
+
+        state.push_input(10);
+        assert_eq!(state.run_until_blocked(), StepResult::NeedInput);
+        assert_eq!(state.drain_output(), vec![10]);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode(1002, 0),
+            Ok(Instruction {
+                opcode: Opcode::Multiply,
+                modes: [Mode::Position, Mode::Immediate, Mode::Position],
+            })
+        );
+        assert_eq!(
+            decode(21107, 0),
+            Ok(Instruction {
+                opcode: Opcode::LessThan,
+                modes: [Mode::Immediate, Mode::Immediate, Mode::Relative],
+            })
+        );
+        assert_eq!(decode(99, 0).unwrap().len(), 1);
+        assert_eq!(
+            decode(-1, 12),
+            Err(IntcodeError::UnknownOpcode { ic: 12, opcode: -1 })
+        );
+        assert_eq!(
+            decode(1305, 3),
+            Err(IntcodeError::BadMode {
+                ic: 3,
+                opcode: 1305,
+                mode: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_unbounded_memory() {
+        // Copies a value far past the end of the program and reads it back
+        let input = vec![1101, 40, 2, 1000000, 4, 1000000, 4, 50000, 99];
+        let mut state = ProgramState::new(&input);
+        assert_eq!(state.run_until_blocked(), StepResult::Halted);
+        assert_eq!(state.drain_output(), vec![42, 0]);
+    }
+
+    #[test]
+    fn test_fork() {
+        // Outputs each input plus one, forever
+        let input = vec![3, 100, 101, 1, 100, 100, 4, 100, 1105, 1, 0];
+        let mut state = ProgramState::new(&input);
+        state.push_input(10);
+        assert_eq!(state.run_until_blocked(), StepResult::NeedInput);
+
+        let mut fork = state.clone();
+        state.push_input(1);
+        fork.push_input(2);
+        assert_eq!(state.run_until_blocked(), StepResult::NeedInput);
+        assert_eq!(fork.run_until_blocked(), StepResult::NeedInput);
+        assert_eq!(state.drain_output(), vec![11, 2]);
+        assert_eq!(fork.drain_output(), vec![11, 3]);
+        assert_eq!(state.memory[100], 2);
+        assert_eq!(fork.memory[100], 3);
+    }
+
+    #[test]
+    fn test_budget() {
+        // Loops forever
+        let mut state = ProgramState::new(&[1101, 1, 1, 5, 1105, 1, 0]);
+        state.budget = Some(100);
+        assert_eq!(run(&mut state), StepResult::BudgetExceeded);
+        assert_eq!((state.ic, state.cycles), (0, 100));
+
+        // Raising the budget lets it carry on from where it stopped
+        state.budget = Some(101);
+        assert_eq!(run(&mut state), StepResult::BudgetExceeded);
+        assert_eq!((state.ic, state.cycles), (4, 101));
+
+        // Blocking on input doesn't count as a cycle
+        let mut state = ProgramState::new(&[3, 0, 4, 0, 99]);
+        assert_eq!(run(&mut state), StepResult::NeedInput);
+        assert_eq!(state.cycles, 0);
+        state.push_input(5);
+        assert_eq!(run(&mut state), StepResult::Output(5));
+        assert_eq!(run(&mut state), StepResult::Halted);
+        assert_eq!(state.cycles, 3);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let input = vec![3, 100, 101, 1, 100, 100, 4, 100, 1105, 1, 0];
+        let mut state = ProgramState::new(&input);
+        state.push_inputs(vec![41, 99]);
+        state.relative_base = -5;
+        state.memory[1 << 30] = -12;
+        assert_eq!(run(&mut state), StepResult::Output(42));
+
+        let path = std::env::temp_dir().join("aoc2019_test_snapshot.txt");
+        state.save_snapshot(&path).unwrap();
+        let mut restored = ProgramState::load_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.to_snapshot(), state.to_snapshot());
+        assert_eq!(restored.ic, 8);
+        assert_eq!(restored.relative_base, -5);
+        assert_eq!(restored.memory[1 << 30], -12);
+        assert_eq!(run(&mut restored), StepResult::Output(100));
+        assert_eq!(run(&mut state), StepResult::Output(100));
+
+        assert_eq!(restored.cycles, 7);
+        assert!(ProgramState::from_snapshot("ic -1").is_err());
+        assert!(ProgramState::from_snapshot("registers 1").is_err());
     }
 }
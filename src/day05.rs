@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::io::prelude::*;
 
-use crate::computer::{run, ProgramState};
+use crate::computer::{run, ProgramState, StepResult};
 
 pub fn load_input() -> Vec<i64> {
     let mut f = File::open("inputs/05.txt").unwrap();
@@ -18,17 +18,25 @@ pub fn load_input() -> Vec<i64> {
 
 pub fn part1(input: &Vec<i64>) -> i64 {
     let mut state = ProgramState::new(input);
-    let mut output = Some(0);
-
-    while output == Some(0) {
-        output = run(1, &mut state);
+    state.push_input(1);
+
+    // Every diagnostic test outputs 0 until the final diagnostic code
+    loop {
+        match run(&mut state) {
+            StepResult::Output(0) => continue,
+            StepResult::Output(code) => return code,
+            _ => panic!("No output returned!"),
+        }
     }
-    output.expect("No output returned!")
 }
 
 pub fn part2(input: &Vec<i64>) -> i64 {
     let mut state = ProgramState::new(input);
-    run(5, &mut state).expect("No output returned!")
+    state.push_input(5);
+    match run(&mut state) {
+        StepResult::Output(code) => code,
+        _ => panic!("No output returned!"),
+    }
 }
 
 #[cfg(test)]
@@ -40,69 +48,87 @@ mod test {
         let input = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(8, &mut state), Some(1));
+        state.push_input(8);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(9, &mut state), Some(0));
+        state.push_input(9);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(7, &mut state), Some(0));
+        state.push_input(7);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let input = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(8, &mut state), Some(0));
+        state.push_input(8);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(9, &mut state), Some(0));
+        state.push_input(9);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(7, &mut state), Some(1));
+        state.push_input(7);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let input = vec![3, 3, 1108, -1, 8, 3, 4, 3, 99];
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(8, &mut state), Some(1));
+        state.push_input(8);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(9, &mut state), Some(0));
+        state.push_input(9);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(7, &mut state), Some(0));
+        state.push_input(7);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let input = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(8, &mut state), Some(0));
+        state.push_input(8);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(9, &mut state), Some(0));
+        state.push_input(9);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(7, &mut state), Some(1));
+        state.push_input(7);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let input =
             vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(0, &mut state), Some(0));
+        state.push_input(0);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(9, &mut state), Some(1));
+        state.push_input(9);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(7, &mut state), Some(1));
+        state.push_input(7);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let input = vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(0, &mut state), Some(0));
+        state.push_input(0);
+        assert_eq!(run(&mut state), StepResult::Output(0));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(9, &mut state), Some(1));
+        state.push_input(9);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(7, &mut state), Some(1));
+        state.push_input(7);
+        assert_eq!(run(&mut state), StepResult::Output(1));
 
         let input = vec![
             3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
@@ -111,12 +137,15 @@ mod test {
         ];
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(8, &mut state), Some(1000));
+        state.push_input(8);
+        assert_eq!(run(&mut state), StepResult::Output(1000));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(9, &mut state), Some(1001));
+        state.push_input(9);
+        assert_eq!(run(&mut state), StepResult::Output(1001));
 
         let mut state = ProgramState::new(&input);
-        assert_eq!(run(7, &mut state), Some(999));
+        state.push_input(7);
+        assert_eq!(run(&mut state), StepResult::Output(999));
     }
 }
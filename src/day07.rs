@@ -1,9 +1,11 @@
 use std::fs::File;
 use std::io::prelude::*;
+use std::sync::mpsc::channel;
 
 use itertools::Itertools;
 
-use crate::computer::{run, ProgramState, ProgramStatus};
+use crate::computer::{run, ProgramState, StepResult};
+use crate::threaded::ThreadedMachine;
 
 pub fn load_input() -> Vec<i64> {
     let mut f = File::open("inputs/07.txt").unwrap();
@@ -26,20 +28,17 @@ pub fn part1(input: &Vec<i64>) -> i64 {
         // Initialize amp program states
         let mut amp_states = Vec::with_capacity(5);
         for _ in 0..5 {
-            amp_states.push(ProgramState {
-                memory: input.clone(),
-                ic: 0,
-                relative_base: 0,
-                status: ProgramStatus::Running,
-            });
+            amp_states.push(ProgramState::new(input));
         }
 
         // Run each amp
         let mut invalue = 0;
         for i in 0..5 {
-            run(phases[i], &mut (amp_states[i]));
-            invalue = run(invalue, &mut (amp_states[i]))
-                .expect("Expected an output!");
+            amp_states[i].push_inputs(vec![phases[i], invalue]);
+            invalue = match run(&mut (amp_states[i])) {
+                StepResult::Output(x) => x,
+                _ => panic!("Expected an output!"),
+            };
         }
 
         if invalue > max_signal {
@@ -54,46 +53,34 @@ pub fn part2(input: &Vec<i64>) -> i64 {
     let mut max_signal = std::i64::MIN;
 
     for phases in phase_perm {
-        let mut amp_states = Vec::with_capacity(5);
-        for _ in 0..5 {
-            amp_states.push(ProgramState {
-                memory: input.clone(),
-                ic: 0,
-                relative_base: 0,
-                status: ProgramStatus::Running,
-            });
-        }
-
-        // Initialize
-        for i in 0..5 {
-            run(phases[i], &mut (amp_states[i]));
-        }
-
-        let mut temp = 0;
-
-        // Loop over feedback
-        loop {
-            let mut break_now = false;
-            for i in 0..5 {
-                let output = run(temp, &mut (amp_states[i]));
-                match amp_states[i].status {
-                    ProgramStatus::Halted => {
-                        break_now = true;
-                        break;
-                    }
-                    ProgramStatus::Running => {
-                        temp = output.unwrap();
-                    }
-                }
+        // Amp i reads from channel i and writes to channel i + 1, with the
+        // last amp feeding back into the first.  Its output is also tapped
+        // so the final signal survives the first amp halting.
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..5).map(|_| channel()).unzip();
+        let (tap, signals) = channel();
+        senders[0].send(0).unwrap();
+
+        let mut amps = Vec::with_capacity(5);
+        for (i, receiver) in receivers.into_iter().enumerate() {
+            let mut state = ProgramState::new(input);
+            state.push_input(phases[i]);
+            let mut outputs = vec![senders[(i + 1) % 5].clone()];
+            if i == 4 {
+                outputs.push(tap.clone());
             }
+            amps.push(ThreadedMachine::spawn(state, receiver, outputs));
+        }
+        drop(senders);
+        drop(tap);
 
-            if break_now {
-                break;
-            }
+        for amp in amps {
+            amp.join().unwrap();
         }
+        let signal = signals.iter().last().expect("Amps never output");
 
-        if temp > max_signal {
-            max_signal = temp;
+        if signal > max_signal {
+            max_signal = signal;
         }
     }
     max_signal
@@ -1,9 +1,8 @@
-extern crate ncurses;
-
 use std::fs::File;
 use std::io::prelude::*;
 
-use ncurses::*;
+use crate::grid::Point;
+use crate::render::{Color, Renderer};
 
 pub fn load_input() -> Vec<u8> {
     let mut f =
@@ -86,38 +85,19 @@ pub fn flatten_layers(layers: &Vec<Layer>) -> Layer {
     output
 }
 
-#[allow(dead_code)]
-pub fn print_layer(layer: &Layer) {
-    // Start ncurses
-    initscr();
-
-    // Print to the back buffer
-    start_color();
-    init_pair(1, COLOR_WHITE, COLOR_BLACK);
-    init_pair(2, COLOR_BLACK, COLOR_BLACK);
-
-    for (y, row) in (&layer).data.iter().enumerate() {
+pub fn print_layer(layer: &Layer, renderer: &mut dyn Renderer) {
+    renderer.clear();
+    for (y, row) in layer.data.iter().enumerate() {
         for (x, pixel) in row.iter().enumerate() {
+            let point = Point::new(x as i32, y as i32);
             if *pixel == 1 {
-                attron(COLOR_PAIR(1));
-                mvprintw(y as i32, x as i32, "#");
-                attroff(COLOR_PAIR(1));
+                renderer.draw_colored(point, "#", Color::White);
             } else {
-                attron(COLOR_PAIR(2));
-                mvprintw(y as i32, x as i32, ".");
-                attroff(COLOR_PAIR(2));
+                renderer.draw_colored(point, ".", Color::Black);
             }
         }
     }
-
-    // Update the screen
-    refresh();
-
-    // Wait for a keypress
-    getch();
-
-    // Terminate ncurses
-    endwin();
+    renderer.present();
 }
 
 pub fn part1(input: &Vec<u8>) -> usize {
@@ -143,7 +123,21 @@ pub fn part1(input: &Vec<u8>) -> usize {
 pub fn part2(input: &Vec<u8>) -> &str {
     let layers = to_layers(input, 25, 6);
     let _out_layer = flatten_layers(&layers);
-    //print_layer(&_out_layer);
 
     "CEKUA"
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::FrameBuffer;
+
+    #[test]
+    fn test_print_layer() {
+        let input = vec![0, 2, 2, 2, 1, 1, 2, 2, 2, 2, 1, 2, 0, 0, 0, 0];
+        let image = flatten_layers(&to_layers(&input, 2, 2));
+        let mut renderer = FrameBuffer::new();
+        print_layer(&image, &mut renderer);
+        assert_eq!(renderer.frames, vec![".#\n#."]);
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::io::prelude::*;
 
-use crate::computer::{run, ProgramState, ProgramStatus};
+use crate::computer::{run, ProgramState, StepResult};
 
 pub fn load_input() -> Vec<i64> {
     let mut f = File::open("inputs/09.txt").unwrap();
@@ -18,12 +18,14 @@ pub fn load_input() -> Vec<i64> {
 
 pub fn part1(input: &Vec<i64>) -> i64 {
     let mut state = ProgramState::new(&input);
+    state.push_input(1);
     let mut value = 0;
     loop {
-        let temp = run(1, &mut state);
-        match state.status {
-            ProgramStatus::Halted => break,
-            ProgramStatus::Running => value = temp.unwrap(),
+        match run(&mut state) {
+            StepResult::Output(x) => value = x,
+            StepResult::Halted => break,
+            StepResult::NeedInput => panic!("BOOST wants more input"),
+            StepResult::BudgetExceeded => unreachable!("BOOST has no budget"),
         }
     }
     value
@@ -31,12 +33,14 @@ pub fn part1(input: &Vec<i64>) -> i64 {
 
 pub fn part2(input: &Vec<i64>) -> i64 {
     let mut state = ProgramState::new(&input);
+    state.push_input(2);
     let mut value = 0;
     loop {
-        let temp = run(2, &mut state);
-        match state.status {
-            ProgramStatus::Halted => break,
-            ProgramStatus::Running => value = temp.unwrap(),
+        match run(&mut state) {
+            StepResult::Output(x) => value = x,
+            StepResult::Halted => break,
+            StepResult::NeedInput => panic!("BOOST wants more input"),
+            StepResult::BudgetExceeded => unreachable!("BOOST has no budget"),
         }
     }
     value
@@ -54,29 +58,22 @@ mod test {
             0, 99,
         ];
         let mut state = ProgramState::new(&input);
-        let mut output = vec![];
-        loop {
-            match state.status {
-                ProgramStatus::Running => {
-                    if let Some(x) = run(0, &mut state) {
-                        output.push(x);
-                    }
-                }
-                ProgramStatus::Halted => break,
-            }
-        }
+        assert_eq!(state.run_until_blocked(), StepResult::Halted);
+        assert_eq!(state.drain_output(), input);
 
         // Outputs a 16 digit number
         let input = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
         let mut state = ProgramState::new(&input);
-        let output = run(0, &mut state).unwrap();
+        let output = match run(&mut state) {
+            StepResult::Output(x) => x,
+            _ => panic!("Expected an output!"),
+        };
         let outlen = output.to_string().chars().collect::<Vec<_>>().len();
         assert_eq!(outlen, 16);
 
         // Outputs the large center number
         let input = vec![104, 1125899906842624, 99];
         let mut state = ProgramState::new(&input);
-        let output = run(0, &mut state).unwrap();
-        assert_eq!(output, input[1]);
+        assert_eq!(run(&mut state), StepResult::Output(input[1]));
     }
 }
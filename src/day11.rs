@@ -1,12 +1,9 @@
-extern crate ncurses;
-
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 
-use crate::computer::{run, ProgramState};
-
-use ncurses::*;
+use crate::computer::{ProgramState, StepResult};
+use crate::grid::{Bounds, Direction, Grid, Point};
+use crate::render::Renderer;
 
 pub fn load_input() -> Vec<i64> {
     let mut f = File::open("inputs/11.txt").unwrap();
@@ -21,190 +18,184 @@ pub fn load_input() -> Vec<i64> {
     output
 }
 
-pub enum Orientation {
-    UP,
-    RIGHT,
-    DOWN,
-    LEFT,
-}
+// Each letter the robot paints is four panels wide and six high, spelled out
+// here row by row with white as `#`
+const LETTERS: [(char, &str); 16] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
 
 pub struct Robot {
-    pub x: i32,
-    pub y: i32,
-    pub orientation: Orientation,
+    pub position: Point,
+    pub direction: Direction,
 }
 
 impl Robot {
     pub fn new() -> Robot {
         Robot {
-            x: 0,
-            y: 0,
-            orientation: Orientation::UP,
+            position: Point::new(0, 0),
+            direction: Direction::North,
         }
     }
 
     pub fn turn(&mut self, turn_dir: u8) {
         // 0 = left, 1 = right
-        match self.orientation {
-            Orientation::UP => {
-                if turn_dir == 0 {
-                    self.orientation = Orientation::LEFT;
-                } else {
-                    self.orientation = Orientation::RIGHT;
-                }
-            }
-            Orientation::RIGHT => {
-                if turn_dir == 0 {
-                    self.orientation = Orientation::UP;
-                } else {
-                    self.orientation = Orientation::DOWN;
-                }
-            }
-            Orientation::DOWN => {
-                if turn_dir == 0 {
-                    self.orientation = Orientation::RIGHT;
-                } else {
-                    self.orientation = Orientation::LEFT;
-                }
-            }
-            Orientation::LEFT => {
-                if turn_dir == 0 {
-                    self.orientation = Orientation::DOWN;
-                } else {
-                    self.orientation = Orientation::UP;
-                }
-            }
+        if turn_dir == 0 {
+            self.direction = self.direction.turn_left();
+        } else {
+            self.direction = self.direction.turn_right();
         }
     }
 
     pub fn move_ahead(&mut self) {
-        match self.orientation {
-            Orientation::UP => {
-                self.y -= 1;
-            }
-            Orientation::RIGHT => {
-                self.x += 1;
-            }
-            Orientation::DOWN => {
-                self.y += 1;
-            }
-            Orientation::LEFT => {
-                self.x -= 1;
-            }
-        }
+        self.position = self.position.step(self.direction);
     }
 }
 
-#[allow(dead_code)]
-pub fn print_panel(panel: &HashMap<(i32, i32), u8>) {
-    let mut min_x = 0;
-    let mut min_y = 0;
-    for (x, y) in panel.keys() {
-        if *x < min_x {
-            min_x = *x;
-        }
-        if *y < min_y {
-            min_y = *y;
-        }
-    }
-
-    // Ncurses stuff
-    initscr();
+pub fn print_panel(panel: &Grid<u8>, renderer: &mut dyn Renderer) {
+    let min = match panel.bounds() {
+        Some(bounds) => bounds.min,
+        None => return,
+    };
 
-    for ((x, y), color) in panel.iter() {
-        if *color == 0 {
-            mvprintw(y - min_y, x - min_x, ".");
-        } else {
-            mvprintw(y - min_y, x - min_x, "#");
-        }
+    renderer.clear();
+    for (point, color) in panel.iter() {
+        let text = if *color == 0 { "." } else { "#" };
+        renderer.draw(point - min, text);
     }
-    refresh();
-    getch();
-    endwin();
+    renderer.present();
 }
 
-pub fn part1(input: &Vec<i64>) -> usize {
-    // Hashmap represents the whole panel, tuple (x, y) is key, value is 0 or 1
-    // to represent the spot on the panel being black or white respectively
-    let mut panel: HashMap<(i32, i32), u8> = HashMap::new();
+/// Runs the robot over a black panel, bar the one it starts on which is
+/// `start`, and returns the panels it painted.  0 is black and 1 white.
+pub fn paint(input: &Vec<i64>, start: u8) -> Grid<u8> {
+    let mut panel: Grid<u8> = Grid::sparse();
     let mut robot = Robot::new();
     let mut state = ProgramState::new(&input);
 
     // Main loop
+    let mut first_loop = true;
     loop {
         let mut curr_color = 0;
-        if let Some(color) = panel.get_mut(&(robot.x, robot.y)) {
+        if first_loop {
+            curr_color = start;
+            first_loop = false;
+        }
+        if let Some(color) = panel.get_mut(robot.position) {
             curr_color = *color;
         }
 
         // 0 = black, 1 = white
-        if let Some(new_color) = run(curr_color as i64, &mut state) {
-            // 0 = left, 1 = right
-            if let Some(turn_dir) = run(0, &mut state) {
-                // Paint current square
-                if let Some(color) = panel.get_mut(&(robot.x, robot.y)) {
-                    *color = new_color as u8;
-                } else {
-                    panel.insert((robot.x, robot.y), new_color as u8);
-                }
-
-                // Turn
-                robot.turn(turn_dir as u8);
-
-                // Move
-                robot.move_ahead();
+        state.push_input(curr_color as i64);
+        let result = state.run_until_blocked();
+
+        // Outputs come in (color, turn) pairs, 0 = left, 1 = right
+        for chunk in state.drain_output().chunks(2) {
+            let (new_color, turn_dir) = (chunk[0], chunk[1]);
+
+            // Paint current square
+            if let Some(color) = panel.get_mut(robot.position) {
+                *color = new_color as u8;
             } else {
-                panic!("Second break, shouldn't see this");
+                panel.insert(robot.position, new_color as u8);
             }
-        } else {
+
+            // Turn
+            robot.turn(turn_dir as u8);
+
+            // Move
+            robot.move_ahead();
+        }
+
+        if result == StepResult::Halted {
             break;
         }
     }
-    panel.len()
+    panel
 }
 
-pub fn part2(input: &Vec<i64>) -> &str {
-    // Hashmap represents the whole panel, tuple (x, y) is key, value is 0 or 1
-    // to represent the spot on the panel being black or white respectively
-    let mut panel: HashMap<(i32, i32), u8> = HashMap::new();
-    let mut robot = Robot::new();
-    let mut state = ProgramState::new(&input);
+pub fn part1(input: &Vec<i64>) -> usize {
+    paint(input, 0).len()
+}
 
-    // Main loop
-    let mut first_loop = true;
-    loop {
-        let mut curr_color = 0;
-        if first_loop {
-            curr_color = 1;
-            first_loop = false;
-        }
-        if let Some(color) = panel.get_mut(&(robot.x, robot.y)) {
-            curr_color = *color;
-        }
+/// Reads the letters painted in white, with a column of panels between
+/// each, or `None` if one of them isn't recognized.
+pub fn read_letters(panel: &Grid<u8>) -> Option<String> {
+    let white = panel.iter().filter(|(_, color)| **color == 1);
+    let bounds = Bounds::of(white.map(|(point, _)| point))?;
+    if bounds.height() != 6 {
+        return None;
+    }
+    (0..(bounds.width() + 4) / 5)
+        .map(|i| {
+            let glyph: String = (0..6)
+                .flat_map(|y| (0..4).map(move |x| Point::new(5 * i + x, y)))
+                .map(|point| match panel.get(bounds.min + point) {
+                    Some(1) => '#',
+                    _ => '.',
+                })
+                .collect();
+            LETTERS
+                .iter()
+                .find(|(_, letters)| *letters == glyph)
+                .map(|(letter, _)| *letter)
+        })
+        .collect()
+}
 
-        // 0 = black, 1 = white
-        if let Some(new_color) = run(curr_color as i64, &mut state) {
-            // 0 = left, 1 = right
-            if let Some(turn_dir) = run(0, &mut state) {
-                // Paint current square
-                if let Some(color) = panel.get_mut(&(robot.x, robot.y)) {
-                    *color = new_color as u8;
-                } else {
-                    panel.insert((robot.x, robot.y), new_color as u8);
-                }
-
-                // Turn
-                robot.turn(turn_dir as u8);
-
-                // Move
-                robot.move_ahead();
-            } else {
-                panic!("Second break, shouldn't see this");
-            }
-        } else {
-            break;
-        }
+pub fn part2(input: &Vec<i64>) -> String {
+    read_letters(&paint(input, 1)).expect("Unrecognized letters")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::FrameBuffer;
+
+    #[test]
+    fn test_print_panel() {
+        let mut panel = Grid::sparse();
+        panel.insert(Point::new(-2, -1), 1);
+        panel.insert(Point::new(-1, -1), 0);
+        panel.insert(Point::new(0, 0), 1);
+        let mut renderer = FrameBuffer::new();
+        print_panel(&panel, &mut renderer);
+        assert_eq!(renderer.frames, vec!["#.\n  #"]);
+    }
+
+    #[test]
+    fn test_read_letters() {
+        let text = "\
+            ..##.#...\n\
+            ...#.#...\n\
+            ...#.#...\n\
+            ...#.#...\n\
+            #..#.#...\n\
+            .##..####";
+        let tile = |c| Some(if c == '#' { 1 } else { 0 });
+        let mut panel = Grid::parse(text, tile);
+        assert_eq!(read_letters(&panel), Some("JL".to_string()));
+
+        panel.insert(Point::new(1, 1), 1);
+        assert_eq!(read_letters(&panel), None);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(&load_input()), "CGPJCGCL");
     }
-    //print_panel(&panel);
-    "CGPJCGCL"
 }
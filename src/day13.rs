@@ -1,12 +1,10 @@
-extern crate ncurses;
-
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 
-use crate::computer::{run, ProgramState};
-
-use ncurses::*;
+use crate::computer::{ProgramState, StepResult};
+use crate::grid::Point;
+use crate::render::Renderer;
 
 pub fn load_input(name: &str) -> Vec<i64> {
     let mut f = File::open(name).unwrap();
@@ -24,14 +22,8 @@ pub fn load_input(name: &str) -> Vec<i64> {
 pub fn part1(input: &Vec<i64>) -> usize {
     let mut state = ProgramState::new(input);
     let mut screen = HashMap::new();
-    let mut output = vec![];
-    loop {
-        if let Some(out) = run(0, &mut state) {
-            output.push(out);
-        } else {
-            break;
-        }
-    }
+    state.run_until_blocked();
+    let output = state.drain_output();
 
     for chunk in output.chunks(3) {
         screen.insert((chunk[0], chunk[1]), chunk[2]);
@@ -46,26 +38,32 @@ pub fn part1(input: &Vec<i64>) -> usize {
     cntr
 }
 
-#[allow(dead_code)]
-pub fn render_screen(screen: &HashMap<(i64, i64), i64>, score: i64) {
+pub fn render_screen(
+    screen: &HashMap<(i64, i64), i64>,
+    score: i64,
+    renderer: &mut dyn Renderer,
+) {
     for ((x, y), id) in screen {
-        match *id {
-            0 => mvprintw(*y as i32, *x as i32, " "),
-            1 => mvprintw(*y as i32, *x as i32, "#"),
-            2 => mvprintw(*y as i32, *x as i32, "-"),
-            3 => mvprintw(*y as i32, *x as i32, "="),
-            4 => mvprintw(*y as i32, *x as i32, "o"),
+        let text = match *id {
+            0 => " ",
+            1 => "#",
+            2 => "-",
+            3 => "=",
+            4 => "o",
             _ => continue,
         };
+        renderer.draw(Point::new(*x as i32, *y as i32), text);
     }
 
     // Render score
-    mvprintw(26, 12, format!("Score: {}", score).as_str());
+    renderer.draw(Point::new(12, 26), &format!("Score: {}", score));
 
-    refresh();
+    renderer.present();
 }
 
-pub fn part2(input: &Vec<i64>) -> u64 {
+/// Plays the game to the end, drawing every frame if given a renderer, and
+/// returns the final score.
+pub fn play(input: &[i64], mut renderer: Option<&mut dyn Renderer>) -> i64 {
     let mut state = ProgramState::new(input);
     state.memory[0] = 2;
     let mut screen = HashMap::new();
@@ -73,14 +71,26 @@ pub fn part2(input: &Vec<i64>) -> u64 {
     let mut ballx = 0;
     let mut paddlex = 0;
 
-    let render_mode = false;
-
-    if render_mode {
-        initscr();
-        curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-    }
     loop {
-        let mut inst = Vec::with_capacity(3);
+        // Run until the cabinet asks for the next joystick position
+        let result = state.run_until_blocked();
+
+        for inst in state.drain_output().chunks(3) {
+            if inst[0] == -1 && inst[1] == 0 {
+                // Update score
+                score = inst[2];
+            } else {
+                screen.insert((inst[0], inst[1]), inst[2]);
+            }
+        }
+
+        if let Some(renderer) = renderer.as_mut() {
+            render_screen(&screen, score, *renderer);
+        }
+
+        if result == StepResult::Halted {
+            break;
+        }
 
         for ((x, _y), id) in &screen {
             if *id == 3 {
@@ -96,42 +106,19 @@ pub fn part2(input: &Vec<i64>) -> u64 {
         } else if ballx < paddlex {
             dir = -1;
         }
-
-        // Grab next instruction
-        if let Some(out) = run(dir, &mut state) {
-            inst.push(out);
-        } else {
-            // Halted
-            break;
-        }
-        if let Some(out) = run(dir, &mut state) {
-            inst.push(out);
-        }
-        if let Some(out) = run(dir, &mut state) {
-            inst.push(out);
-        }
-
-        if inst[0] == -1 && inst[1] == 0 {
-            // Update score
-            score = inst[2];
-        } else {
-            screen.insert((inst[0], inst[1]), inst[2]);
-        }
-
-        if render_mode {
-            render_screen(&screen, score);
-        }
+        state.push_input(dir);
     }
-    if render_mode {
-        curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-        endwin();
-    }
-    score as u64
+    score
+}
+
+pub fn part2(input: &Vec<i64>) -> u64 {
+    play(input, None) as u64
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::render::FrameBuffer;
 
     #[test]
     fn test_part1() {
@@ -142,4 +129,22 @@ mod test {
     fn test_part2() {
         assert_eq!(0, 0);
     }
+
+    #[test]
+    fn test_render_screen() {
+        let mut screen = HashMap::new();
+        for x in 0..5 {
+            screen.insert((x, 0), 1);
+        }
+        screen.insert((1, 1), 2);
+        screen.insert((3, 1), 4);
+        screen.insert((2, 2), 3);
+
+        let mut renderer = FrameBuffer::new();
+        render_screen(&screen, 42, &mut renderer);
+        let frame = renderer.frames[0].clone();
+        let lines: Vec<&str> = frame.lines().collect();
+        assert_eq!(lines[..3], ["#####", " - o", "  ="]);
+        assert_eq!(lines[26], "            Score: 42");
+    }
 }
@@ -1,13 +1,11 @@
-extern crate ncurses;
-
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::prelude::*;
-use std::{thread, time};
-
-use crate::computer::{run, ProgramState};
 
-use ncurses::*;
+use crate::computer::{run, ProgramState, StepResult};
+use crate::grid::{Direction, Grid, Point};
+use crate::pathfind;
+use crate::render::Renderer;
 
 pub fn load_input(name: &str) -> Vec<i64> {
     let mut f = File::open(name).unwrap();
@@ -22,12 +20,14 @@ pub fn load_input(name: &str) -> Vec<i64> {
     output
 }
 
-#[derive(Debug, Clone)]
-pub enum Move {
-    North = 1,
-    South,
-    West,
-    East,
+// The droid's movement commands
+pub fn command(dir: Direction) -> i64 {
+    match dir {
+        Direction::North => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+        Direction::East => 4,
+    }
 }
 
 pub enum Status {
@@ -47,7 +47,7 @@ impl From<i64> for Status {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Block {
     Wall,
     Open,
@@ -55,256 +55,152 @@ pub enum Block {
 }
 
 pub struct Droid {
-    pub x: i32,
-    pub y: i32,
-}
-
-pub fn next_pos(pos: &(i32, i32), dir: &Move) -> (i32, i32) {
-    let mut output = pos.clone();
-    match dir {
-        Move::North => output.1 -= 1,
-        Move::South => output.1 += 1,
-        Move::West => output.0 -= 1,
-        Move::East => output.0 += 1,
-    }
-    output
+    pub position: Point,
 }
 
 impl Droid {
     pub fn new(x: i32, y: i32) -> Droid {
-        Droid { x, y }
+        Droid {
+            position: Point::new(x, y),
+        }
     }
 
-    pub fn move_dir(&mut self, dir: &Move) {
-        let new = next_pos(&(self.x, self.y), dir);
-        self.x = new.0;
-        self.y = new.1;
+    pub fn move_dir(&mut self, dir: Direction) {
+        self.position = self.position.step(dir);
     }
 }
 
 #[derive(Debug)]
 pub struct Map {
-    pub map: HashMap<(i32, i32), (Block, Vec<Move>)>,
-    pub unexplored: HashSet<(i32, i32)>,
+    pub map: Grid<Block>,
+    pub unexplored: HashSet<Point>,
 }
 
 impl Map {
     pub fn new() -> Map {
-        let mut map = HashMap::new();
-        map.insert((0, 0), (Block::Open, vec![]));
+        let origin = Point::new(0, 0);
+        let mut map = Grid::sparse();
+        map.insert(origin, Block::Open);
 
-        let mut set = HashSet::new();
-        set.insert(next_pos(&(0, 0), &Move::North));
-        set.insert(next_pos(&(0, 0), &Move::South));
-        set.insert(next_pos(&(0, 0), &Move::West));
-        set.insert(next_pos(&(0, 0), &Move::East));
         Map {
             map: map,
-            unexplored: set,
+            unexplored: origin.neighbours().collect(),
         }
     }
 
-    pub fn insert(&mut self, key: (i32, i32), value: (Block, Vec<Move>)) {
-        if let Some(tempvalue) = self.map.get_mut(&key) {
-            // If already present, update path if and only if shorter
-            if tempvalue.1.len() > value.1.len() {
-                tempvalue.1 = value.1.to_vec();
-            }
-        } else {
-            // If not already present, insert it
-            self.map.insert(key, value);
-        }
-        if let Some(_value) = self.unexplored.get(&key) {
-            self.unexplored.remove(&key);
-        }
+    pub fn insert(&mut self, key: Point, block: Block) {
+        self.map.insert(key, block);
+        self.unexplored.remove(&key);
     }
 
-    pub fn update_unexplored(&mut self, pos: &(i32, i32)) {
+    pub fn update_unexplored(&mut self, pos: Point) {
         // Adds new unexplored tiles to four adjacent squares to pos.
-        let mut new_dirs = vec![];
-        new_dirs.push(next_pos(pos, &Move::North));
-        new_dirs.push(next_pos(pos, &Move::South));
-        new_dirs.push(next_pos(pos, &Move::West));
-        new_dirs.push(next_pos(pos, &Move::East));
-
-        for new in new_dirs {
-            if let Some(_value) = self.map.get(&new) {
-                continue;
-            } else {
+        for new in pos.neighbours() {
+            if !self.map.contains(new) {
                 self.unexplored.insert(new);
             }
         }
     }
 
-    pub fn path(&self, p1: &(i32, i32), p2: &(i32, i32)) -> Vec<Move> {
-        let mut cloud: HashMap<(i32, i32), Vec<Move>> = HashMap::new();
-        cloud.insert(*p1, vec![]);
-
-        loop {
-            // Find new_points
-            let mut new_points = HashMap::new();
-            for (point, path) in &cloud {
-                for tup in neighboring_grid_points(point) {
-                    let new_pt = (tup.0, tup.1);
-                    let mut new_path = path.clone();
-                    new_path.push(tup.2);
-                    if !cloud.contains_key(&new_pt) {
-                        new_points.insert(new_pt, new_path);
-                    }
-                }
-            }
-
-            // Insert new_points into cloud
-            for (point, path) in &new_points {
-                if let Some((tile, _)) = self.map.get(point) {
-                    match tile {
-                        Block::Wall => continue,
-                        _ => (),
-                    }
-                }
-
-                if let Some(existing_path) = cloud.get_mut(point) {
-                    if path.len() < existing_path.len() {
-                        *existing_path = (*path).clone();
-                    }
-                } else {
-                    cloud.insert(point.clone(), path.clone());
-                }
-            }
-
-            if cloud.contains_key(p2) {
-                break;
-            }
-        }
-
-        (*cloud.get(p2).unwrap()).clone()
+    pub fn path(&self, p1: Point, p2: Point) -> Vec<Direction> {
+        // Unexplored points might be open, so only steer around known walls
+        let neighbours = |pt: &Point| {
+            pt.neighbours()
+                .filter(|new_pt| {
+                    !matches!(self.map.get(*new_pt), Some(Block::Wall))
+                })
+                .collect::<Vec<_>>()
+        };
+        let search = pathfind::astar(
+            p1,
+            neighbours,
+            |_, _| 1,
+            |pt| pt.manhattan(p2),
+            |pt| *pt == p2,
+        );
+
+        // Turn the points along the way into moves between them
+        let points = search.goal_path().expect("No path to point");
+        points
+            .windows(2)
+            .map(|pair| {
+                *Direction::ALL
+                    .iter()
+                    .find(|dir| pair[0].step(**dir) == pair[1])
+                    .unwrap()
+            })
+            .collect()
     }
 
-    pub fn expand_oxygen(&mut self) -> i64 {
-        let mut oxypt = (0, 0);
-        for (point, (tile, _path)) in &self.map {
-            match tile {
-                Block::Oxygen => {
-                    oxypt = point.clone();
-                }
-                _ => continue,
-            }
-        }
-
-        let mut cloud: HashMap<(i32, i32), Vec<Move>> = HashMap::new();
-        cloud.insert(oxypt, vec![]);
-
-        let mut t = 1;
-        loop {
-            // Find new_points
-            let mut new_points = HashMap::new();
-            for (point, path) in &cloud {
-                for tup in neighboring_grid_points(point) {
-                    let new_pt = (tup.0, tup.1);
-                    let mut new_path = path.clone();
-                    new_path.push(tup.2);
-                    if !cloud.contains_key(&new_pt) {
-                        new_points.insert(new_pt, new_path);
-                    }
-                }
-            }
-
-            // Insert new_points into cloud
-            for (point, path) in &new_points {
-                if let Some((tile, _)) = self.map.get(point) {
-                    match tile {
-                        Block::Wall => continue,
-                        _ => (),
-                    }
-                }
-
-                if let Some(existing_path) = cloud.get_mut(point) {
-                    if path.len() < existing_path.len() {
-                        *existing_path = (*path).clone();
-                    }
-                } else {
-                    cloud.insert(point.clone(), path.clone());
-                }
-            }
-
-            /*
-            // Only needed for pretty render
-            for (point, _) in &cloud {
-                if let Some(tup) = self.map.get_mut(point) {
-                    tup.0 = Block::Oxygen;
-                }
-            }
-            self.render(&Droid::new(0, 0), format!("t: {}", t).as_str());
-            */
+    pub fn oxygen(&self) -> Point {
+        self.map
+            .iter()
+            .find(|(_, tile)| matches!(tile, Block::Oxygen))
+            .map(|(point, _)| point)
+            .expect("No oxygen system on the map")
+    }
 
-            // See if every open point in map is in cloud yet
-            let mut done = true;
-            for (point, (tile, _path)) in &self.map {
-                match tile {
-                    Block::Open => {
-                        if !cloud.contains_key(point) {
-                            done = false;
-                        }
-                    }
-                    _ => (),
-                }
-            }
+    // Known points next to `pt` that the droid can move onto
+    fn open_neighbours(&self, pt: &Point) -> Vec<Point> {
+        self.map
+            .neighbours(*pt)
+            .filter(|(_, tile)| !matches!(tile, Block::Wall))
+            .map(|(new_pt, _)| new_pt)
+            .collect()
+    }
 
-            if done {
-                break;
-            }
+    // Fewest moves from where the droid started to the oxygen system
+    pub fn distance_to_oxygen(&self) -> i64 {
+        let oxypt = self.oxygen();
+        let search = pathfind::bfs(
+            Point::new(0, 0),
+            |pt| self.open_neighbours(pt),
+            |pt| *pt == oxypt,
+        );
+        search
+            .goal_distance()
+            .expect("No path to the oxygen system") as i64
+    }
 
-            t += 1;
-        }
-        t
+    // Minutes for oxygen to spread from the system to every open point
+    pub fn expand_oxygen(&self) -> i64 {
+        let search = pathfind::bfs(
+            self.oxygen(),
+            |pt| self.open_neighbours(pt),
+            |_| false,
+        );
+        *search.distances.values().max().unwrap() as i64
     }
 
-    #[allow(dead_code)]
-    pub fn render(&self, droid: &Droid, text: &str) {
-        let mut min_x = -25;
-        let mut min_y = -25;
-        for (x, y) in self.map.keys() {
-            if *x < min_x {
-                min_x = *x;
-            }
-            if *y < min_y {
-                min_y = *y;
-            }
-        }
-        initscr();
-        curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-        clear();
-        for ((x, y), (block, _path)) in &self.map {
-            match block {
-                Block::Wall => mvprintw(y - min_y, x - min_x, "#"),
-                Block::Open => mvprintw(y - min_y, x - min_x, "."),
-                Block::Oxygen => mvprintw(y - min_y, x - min_x, "O"),
+    pub fn render(
+        &self,
+        droid: &Droid,
+        text: &str,
+        renderer: &mut dyn Renderer,
+    ) {
+        let bounds = self.map.bounds().unwrap();
+        let min = Point::new(bounds.min.x.min(-25), bounds.min.y.min(-25));
+        renderer.clear();
+        for (point, block) in self.map.iter() {
+            let text = match block {
+                Block::Wall => "#",
+                Block::Open => ".",
+                Block::Oxygen => "O",
             };
+            renderer.draw(point - min, text);
         }
-        mvprintw(droid.y - min_y, droid.x - min_x, "D");
-        mvprintw(50, 0, text);
-
-        refresh();
-        thread::sleep(time::Duration::from_millis(33));
-        curs_set(CURSOR_VISIBILITY::CURSOR_VISIBLE);
-        endwin();
+        renderer.draw(droid.position - min, "D");
+        renderer.draw(Point::new(0, 50), text);
+        renderer.present();
     }
 }
 
-pub fn dist(p1: &(i32, i32), p2: &(i32, i32)) -> i32 {
-    (p1.0 - p2.0).abs() + (p1.1 - p2.1).abs()
-}
-
-pub fn neighboring_grid_points(pt: &(i32, i32)) -> Vec<(i32, i32, Move)> {
-    let npt = (pt.0, pt.1 - 1, Move::North);
-    let spt = (pt.0, pt.1 + 1, Move::South);
-    let ept = (pt.0 + 1, pt.1, Move::East);
-    let wpt = (pt.0 - 1, pt.1, Move::West);
-
-    vec![npt, spt, ept, wpt]
-}
-
-pub fn explore_entire_map(input: &Vec<i64>) -> (Map, Droid, ProgramState) {
+/// Moves the droid until every reachable point is known, drawing each move
+/// if given a renderer.
+pub fn explore_entire_map(
+    input: &[i64],
+    mut renderer: Option<&mut dyn Renderer>,
+) -> (Map, Droid, ProgramState) {
     let mut state = ProgramState::new(input);
     let mut map = Map::new();
     let mut droid = Droid::new(0, 0);
@@ -312,11 +208,11 @@ pub fn explore_entire_map(input: &Vec<i64>) -> (Map, Droid, ProgramState) {
     // Explore the entire map
     loop {
         if map.unexplored.len() > 0 {
-            let droid_pos = (droid.x, droid.y);
+            let droid_pos = droid.position;
             let mut min_dist = std::i32::MAX;
             let mut min_pt = droid_pos;
             for pt in &map.unexplored {
-                let new_dist = dist(&droid_pos, &pt);
+                let new_dist = droid_pos.manhattan(*pt);
                 if new_dist < min_dist {
                     min_dist = new_dist;
                     min_pt = *pt;
@@ -325,48 +221,37 @@ pub fn explore_entire_map(input: &Vec<i64>) -> (Map, Droid, ProgramState) {
             let target_pt = min_pt;
 
             // Path to target point
-            let command_seq = &map.path(&(droid.x, droid.y), &target_pt);
-            //map.render(&droid, format!("pos: {:?}\ntarget: {:?}\nseq: {:?}", (droid.x, droid.y), target_pt, command_seq).as_str());
+            let command_seq = &map.path(droid.position, target_pt);
             for command in command_seq {
                 if !map.unexplored.contains(&target_pt) {
                     break;
                 }
-                let status = Status::from(
-                    run(command.clone() as i64, &mut state).unwrap(),
-                );
-
-                let mut new_path =
-                    (&map.map.get(&(droid.x, droid.y)).unwrap().1).clone();
-                new_path.push(command.clone());
+                state.push_input(self::command(*command));
+                let status = match run(&mut state) {
+                    StepResult::Output(x) => Status::from(x),
+                    _ => panic!("Droid stopped reporting status"),
+                };
 
                 match status {
                     Status::HitWall => {
-                        let pos = (droid.x, droid.y);
-                        droid.move_dir(&command);
-                        map.insert(
-                            (droid.x, droid.y),
-                            (Block::Wall, new_path.to_vec()),
-                        );
-                        droid.x = pos.0;
-                        droid.y = pos.1;
+                        let wall = droid.position.step(*command);
+                        map.insert(wall, Block::Wall);
                     }
                     Status::MoveSuccess => {
-                        droid.move_dir(&command);
-                        map.insert(
-                            (droid.x, droid.y),
-                            (Block::Open, new_path.to_vec()),
-                        );
-                        map.update_unexplored(&(droid.x, droid.y));
+                        droid.move_dir(*command);
+                        map.insert(droid.position, Block::Open);
+                        map.update_unexplored(droid.position);
                     }
                     Status::FoundOxygen => {
-                        droid.move_dir(&command);
-                        map.insert(
-                            (droid.x, droid.y),
-                            (Block::Oxygen, new_path.to_vec()),
-                        );
-                        map.update_unexplored(&(droid.x, droid.y));
+                        droid.move_dir(*command);
+                        map.insert(droid.position, Block::Oxygen);
+                        map.update_unexplored(droid.position);
                     }
                 }
+                if let Some(renderer) = renderer.as_mut() {
+                    let text = format!("{} to explore", map.unexplored.len());
+                    map.render(&droid, &text, *renderer);
+                }
             }
         } else {
             // unexplored is now size 0, done exploring!
@@ -377,18 +262,49 @@ pub fn explore_entire_map(input: &Vec<i64>) -> (Map, Droid, ProgramState) {
 }
 
 pub fn part1(input: &Vec<i64>) -> i64 {
-    let (map, _droid, _state) = explore_entire_map(input);
-
-    for (_pt, (block, path)) in map.map {
-        match block {
-            Block::Oxygen => return path.len() as i64,
-            _ => (),
-        }
-    }
-    0
+    let (map, _droid, _state) = explore_entire_map(input, None);
+    map.distance_to_oxygen()
 }
 
 pub fn part2(input: &Vec<i64>) -> i64 {
-    let (mut map, _droid, _state) = explore_entire_map(input);
+    let (map, _droid, _state) = explore_entire_map(input, None);
     map.expand_oxygen()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // The example from part 2, with the droid starting at the right end of
+    // the top row
+    const AREA: &str = "\
+ ##
+#..##
+#.#..#
+#.O.#
+ ###
+";
+
+    fn map(text: &str) -> Map {
+        let mut map = Map::new();
+        let start = Point::new(2, 1);
+        let grid = Grid::parse(text, |c| match c {
+            '#' => Some(Block::Wall),
+            '.' => Some(Block::Open),
+            'O' => Some(Block::Oxygen),
+            _ => None,
+        });
+        for (point, block) in grid.iter() {
+            map.insert(point - start, *block);
+        }
+        map
+    }
+
+    #[test]
+    fn test_oxygen() {
+        let map = map(AREA);
+        assert_eq!(map.oxygen(), Point::new(0, 2));
+        assert_eq!(map.distance_to_oxygen(), 4);
+        assert_eq!(map.expand_oxygen(), 4);
+    }
+}
@@ -1,12 +1,9 @@
-extern crate ncurses;
-
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 
-use crate::computer::{run, ProgramState};
-
-use ncurses::*;
+use crate::ascii::AsciiMachine;
+use crate::grid::{Direction, Grid, Point};
+use crate::render::Renderer;
 
 pub fn load_input(name: &str) -> Vec<i64> {
     let mut f = File::open(name).unwrap();
@@ -25,172 +22,274 @@ pub fn load_input(name: &str) -> Vec<i64> {
 pub enum Tile {
     Scaffold,
     Open,
-    RobotUp,
-    RobotDown,
-    RobotLeft,
-    RobotRight,
+    Robot(Direction),
 }
 
-#[allow(dead_code)]
-pub fn render(map: &HashMap<(i32, i32), Tile>) {
-    let mut min_x = 0;
-    let mut min_y = 0;
-
-    initscr();
-    curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-    clear();
-    for ((x, y), tile) in map {
-        if *x < min_x {
-            min_x = *x;
-        }
-        if *y < min_y {
-            min_y = *y;
-        }
-        match *tile {
-            Tile::Scaffold => mvprintw(*y - min_y, *x - min_x, "#"),
-            Tile::Open => mvprintw(*y - min_y, x - min_x, "."),
-            Tile::RobotUp => mvprintw(*y - min_y, x - min_x, "^"),
-            Tile::RobotDown => mvprintw(*y - min_y, x - min_x, "v"),
-            Tile::RobotLeft => mvprintw(*y - min_y, x - min_x, "<"),
-            Tile::RobotRight => mvprintw(*y - min_y, x - min_x, ">"),
+pub fn render(map: &Grid<Tile>, renderer: &mut dyn Renderer) {
+    renderer.clear();
+    for (point, tile) in map.iter() {
+        let text = match *tile {
+            Tile::Scaffold => "#",
+            Tile::Open => ".",
+            Tile::Robot(Direction::North) => "^",
+            Tile::Robot(Direction::South) => "v",
+            Tile::Robot(Direction::West) => "<",
+            Tile::Robot(Direction::East) => ">",
         };
+        renderer.draw(point, text);
     }
-    refresh();
-    getch();
-    curs_set(CURSOR_VISIBILITY::CURSOR_VISIBLE);
-    endwin();
+    renderer.present();
 }
 
-pub fn parse_input(input: &Vec<i64>) -> HashMap<(i32, i32), Tile> {
-    let mut output = HashMap::new();
+pub fn parse_map(text: &str) -> Grid<Tile> {
+    Grid::parse(text, |c| {
+        Some(match c {
+            '#' => Tile::Scaffold,
+            '.' => Tile::Open,
+            '^' => Tile::Robot(Direction::North),
+            'v' => Tile::Robot(Direction::South),
+            '<' => Tile::Robot(Direction::West),
+            '>' => Tile::Robot(Direction::East),
+            _ => panic!("Output {:?} unrecognized", c),
+        })
+    })
+}
+
+pub fn parse_input(input: &[i64]) -> Grid<Tile> {
+    parse_map(&AsciiMachine::new(input).run().text)
+}
 
-    let mut state = ProgramState::new(input);
+pub fn is_intersection(pt: Point, map: &Grid<Tile>) -> bool {
+    let is_scaffold =
+        |tile: Option<&Tile>| matches!(tile, Some(Tile::Scaffold));
+    is_scaffold(map.get(pt))
+        && pt
+            .neighbours()
+            .all(|neighbour| is_scaffold(map.get(neighbour)))
+}
 
-    let mut x = 0;
-    let mut y = 0;
+pub fn part1(input: &[i64]) -> i64 {
+    let map = parse_input(input);
+
+    let mut ap_sum = 0;
+    for point in map.points() {
+        if is_intersection(point, &map) {
+            ap_sum += (point.x * point.y) as i64;
+        }
+    }
+
+    ap_sum
+}
+
+// The robot stands on scaffold too
+fn is_scaffold(map: &Grid<Tile>, point: Point) -> bool {
+    match map.get(point) {
+        Some(Tile::Open) | None => false,
+        Some(_) => true,
+    }
+}
+
+/// Follows the scaffold from the robot to its far end, going straight for
+/// as long as possible before turning.  Returns the turns and distances as
+/// separate instructions, e.g. `["R", "8", "L", "10"]`.
+pub fn trace_path(map: &Grid<Tile>) -> Vec<String> {
+    let (mut point, mut dir) = map
+        .iter()
+        .find_map(|(point, tile)| match tile {
+            Tile::Robot(dir) => Some((point, *dir)),
+            _ => None,
+        })
+        .expect("No robot on the map");
+
+    let mut path = vec![];
     loop {
-        if let Some(out) = run(0, &mut state) {
-            match out {
-                10 => {
-                    // LF
-                    y += 1;
-                    x = 0;
-                }
-                35 => {
-                    // #
-                    output.insert((x, y), Tile::Scaffold);
-                    x += 1;
-                }
-                46 => {
-                    // .
-                    output.insert((x, y), Tile::Open);
-                    x += 1;
-                }
-                94 => {
-                    // ^
-                    output.insert((x, y), Tile::RobotUp);
-                    x += 1;
-                }
-                118 => {
-                    // v
-                    output.insert((x, y), Tile::RobotDown);
-                    x += 1;
-                }
-                60 => {
-                    // <
-                    output.insert((x, y), Tile::RobotLeft);
-                    x += 1;
-                }
-                62 => {
-                    // >
-                    output.insert((x, y), Tile::RobotRight);
-                    x += 1;
-                }
-                _ => panic!("Output {} unrecognized"),
-            }
+        let (turn, new_dir) = if is_scaffold(map, point.step(dir.turn_left())) {
+            ("L", dir.turn_left())
+        } else if is_scaffold(map, point.step(dir.turn_right())) {
+            ("R", dir.turn_right())
         } else {
-            break;
+            return path;
+        };
+        dir = new_dir;
+
+        let mut distance = 0;
+        while is_scaffold(map, point.step(dir)) {
+            point = point.step(dir);
+            distance += 1;
         }
+        path.push(turn.to_string());
+        path.push(distance.to_string());
     }
+}
 
-    output
+// Length of the tokens written out with commas between them
+fn joined_len(tokens: &[&str]) -> usize {
+    tokens.iter().map(|token| token.len()).sum::<usize>() + tokens.len() - 1
 }
 
-pub fn is_intersection(
-    pt: &(i32, i32),
-    map: &HashMap<(i32, i32), Tile>,
+fn compress_from<'a>(
+    rest: &[&'a str],
+    n_functions: usize,
+    max_len: usize,
+    main: &mut Vec<usize>,
+    functions: &mut Vec<Vec<&'a str>>,
 ) -> bool {
-    let x = pt.0;
-    let y = pt.1;
-
-    // Current point
-    if let Some(tile) = map.get(&(x, y)) {
-        match tile {
-            Tile::Scaffold => (),
-            _ => return false,
-        }
-    } else {
-        return false;
+    if rest.is_empty() {
+        return true;
     }
-
-    // Above
-    if let Some(tile) = map.get(&(x, y - 1)) {
-        match tile {
-            Tile::Scaffold => (),
-            _ => return false,
-        }
-    } else {
+    // One more call has to fit in the main routine
+    if 2 * main.len() + 1 > max_len {
         return false;
     }
 
-    // Below
-    if let Some(tile) = map.get(&(x, y + 1)) {
-        match tile {
-            Tile::Scaffold => (),
-            _ => return false,
+    for i in 0..functions.len() {
+        if rest.starts_with(&functions[i]) {
+            let len = functions[i].len();
+            main.push(i);
+            if compress_from(
+                &rest[len..],
+                n_functions,
+                max_len,
+                main,
+                functions,
+            ) {
+                return true;
+            }
+            main.pop();
         }
-    } else {
-        return false;
     }
 
-    // Left
-    if let Some(tile) = map.get(&(x - 1, y)) {
-        match tile {
-            Tile::Scaffold => (),
-            _ => return false,
+    if functions.len() < n_functions {
+        for len in 1..=rest.len() {
+            if joined_len(&rest[..len]) > max_len {
+                break;
+            }
+            main.push(functions.len());
+            functions.push(rest[..len].to_vec());
+            if compress_from(
+                &rest[len..],
+                n_functions,
+                max_len,
+                main,
+                functions,
+            ) {
+                return true;
+            }
+            functions.pop();
+            main.pop();
         }
-    } else {
-        return false;
     }
+    false
+}
 
-    // Right
-    if let Some(tile) = map.get(&(x + 1, y)) {
-        match tile {
-            Tile::Scaffold => (),
-            _ => return false,
-        }
+/// Splits `tokens` into a main routine of calls to at most `n_functions`
+/// functions, where the main routine and every function fit in `max_len`
+/// characters once written out with commas.  Returns the main routine as
+/// function indices along with each function's tokens.
+pub fn compress<'a>(
+    tokens: &[&'a str],
+    n_functions: usize,
+    max_len: usize,
+) -> Option<(Vec<usize>, Vec<Vec<&'a str>>)> {
+    let mut main = vec![];
+    let mut functions = vec![];
+    if compress_from(tokens, n_functions, max_len, &mut main, &mut functions) {
+        Some((main, functions))
     } else {
-        return false;
+        None
     }
-
-    true
 }
 
-pub fn part1(input: &Vec<i64>) -> i64 {
+pub fn part2(input: &[i64]) -> i64 {
     let map = parse_input(input);
+    let path = trace_path(&map);
+    let tokens: Vec<&str> = path.iter().map(|token| token.as_str()).collect();
+    let (main, functions) =
+        compress(&tokens, 3, 20).expect("Path doesn't fit in 3 functions");
 
-    let mut ap_sum = 0;
-    for (point, _tile) in &map {
-        if is_intersection(&point, &map) {
-            ap_sum += (point.0 * point.1) as i64;
-        }
+    let names: Vec<String> = main
+        .iter()
+        .map(|i| ((b'A' + *i as u8) as char).to_string())
+        .collect();
+    let mut lines = vec![names.join(",")];
+    lines.extend(functions.iter().map(|function| function.join(",")));
+    // Unused functions still need a definition
+    while lines.len() < 4 {
+        lines.push("L".to_string());
     }
+    // No continuous video feed
+    lines.push("n".to_string());
 
-    ap_sum
+    let mut robot = AsciiMachine::new(input);
+    robot.state.memory[0] = 2;
+    robot.send_lines(lines.iter().map(|line| line.as_str()));
+    robot
+        .run()
+        .answer
+        .expect("Robot didn't report the dust collected")
 }
 
-pub fn part2(input: &Vec<i64>) -> i64 {
-    let map = parse_input(input);
-    render(&map);
-    0
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::FrameBuffer;
+
+    const EXAMPLE: &str = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......
+";
+
+    #[test]
+    fn test_render() {
+        let mut renderer = FrameBuffer::new();
+        render(&parse_map(EXAMPLE), &mut renderer);
+        assert_eq!(renderer.frames, vec![EXAMPLE.trim_end()]);
+    }
+
+    #[test]
+    fn test_trace_path() {
+        let path = trace_path(&parse_map(EXAMPLE));
+        assert_eq!(
+            path.join(","),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+    }
+
+    #[test]
+    fn test_compress() {
+        let path = trace_path(&parse_map(EXAMPLE));
+        let tokens: Vec<&str> = path.iter().map(|t| t.as_str()).collect();
+        let (main, functions) = compress(&tokens, 3, 20).unwrap();
+
+        let expanded: Vec<&str> = main
+            .iter()
+            .flat_map(|i| functions[*i].iter().cloned())
+            .collect();
+        assert_eq!(expanded, tokens);
+        assert!(functions.len() <= 3);
+        assert!(2 * main.len() - 1 <= 20);
+        for function in &functions {
+            assert!(function.join(",").len() <= 20);
+        }
+
+        // Too tight a limit has no answer
+        assert_eq!(compress(&tokens, 3, 5), None);
+        assert_eq!(
+            compress(&["R", "8"], 1, 3),
+            Some((vec![0], vec![vec!["R", "8"]]))
+        );
+    }
 }
@@ -0,0 +1,329 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+pub fn load_input(name: &str) -> Vec<String> {
+    let f = BufReader::new(File::open(name).unwrap());
+    f.lines().map(|x| x.unwrap()).collect()
+}
+
+/// The shortest walk from one place to a key, and the doors in the way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Route {
+    pub key: usize,
+    pub distance: usize,
+    pub doors: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Vault {
+    pub grid: Vec<Vec<char>>,
+    pub entrances: Vec<(usize, usize)>,
+}
+
+fn key_bit(c: char) -> u32 {
+    1 << (c.to_ascii_lowercase() as u8 - b'a')
+}
+
+impl Vault {
+    pub fn parse(input: &[String]) -> Vault {
+        let grid: Vec<Vec<char>> = input
+            .iter()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().collect())
+            .collect();
+        let mut entrances = vec![];
+        for (y, row) in grid.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                if *c == '@' {
+                    entrances.push((x, y));
+                }
+            }
+        }
+        Vault { grid, entrances }
+    }
+
+    /// Walls off a single entrance and puts a robot in each corner around
+    /// it, giving the four vaults of part 2.
+    pub fn split_entrance(&mut self) {
+        assert_eq!(self.entrances.len(), 1, "Vault already split");
+        let (x, y) = self.entrances[0];
+        let patch = ["@#@", "###", "@#@"];
+        self.entrances.clear();
+        for (dy, row) in patch.iter().enumerate() {
+            for (dx, c) in row.chars().enumerate() {
+                let point = (x + dx - 1, y + dy - 1);
+                self.grid[point.1][point.0] = c;
+                if c == '@' {
+                    self.entrances.push(point);
+                }
+            }
+        }
+    }
+
+    /// Positions of the keys, indexed by letter.
+    fn keys(&self) -> Vec<Option<(usize, usize)>> {
+        let mut keys = vec![None; 26];
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                if c.is_ascii_lowercase() {
+                    keys[(*c as u8 - b'a') as usize] = Some((x, y));
+                }
+            }
+        }
+        keys
+    }
+
+    /// Every key reachable from `start`, walking through doors and other
+    /// keys but noting the doors passed.
+    pub fn routes(&self, start: (usize, usize)) -> Vec<Route> {
+        let mut routes = vec![];
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(start);
+        queue.push_back((start, 0, 0));
+
+        while let Some(((x, y), distance, doors)) = queue.pop_front() {
+            let c = self.grid[y][x];
+            if c.is_ascii_lowercase() && distance > 0 {
+                routes.push(Route {
+                    key: (c as u8 - b'a') as usize,
+                    distance,
+                    doors,
+                });
+            }
+            let doors = if c.is_ascii_uppercase() {
+                doors | key_bit(c)
+            } else {
+                doors
+            };
+
+            for &(nx, ny) in &[(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)] {
+                if self.grid[ny][nx] != '#' && seen.insert((nx, ny)) {
+                    queue.push_back(((nx, ny), distance + 1, doors));
+                }
+            }
+        }
+        routes
+    }
+
+    /// Fewest steps for the robots to collect every key.  Searches over
+    /// which key (or entrance) each robot is standing at and which keys
+    /// are held, using the precomputed routes between them.
+    pub fn shortest_path(&self) -> Option<usize> {
+        let keys = self.keys();
+        let all_keys = keys
+            .iter()
+            .enumerate()
+            .filter(|(_, k)| k.is_some())
+            .fold(0, |all, (i, _)| all | 1 << i);
+
+        // Places 0..26 are keys, and the entrances follow
+        let mut routes = vec![vec![]; 26 + self.entrances.len()];
+        for (i, key) in keys.iter().enumerate() {
+            if let Some(point) = key {
+                routes[i] = self.routes(*point);
+            }
+        }
+        for (i, entrance) in self.entrances.iter().enumerate() {
+            routes[26 + i] = self.routes(*entrance);
+        }
+
+        let start: Vec<usize> = (26..26 + self.entrances.len()).collect();
+        let mut best = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        best.insert((start.clone(), 0u32), 0);
+        heap.push(Reverse((0, start, 0u32)));
+
+        while let Some(Reverse((distance, robots, held))) = heap.pop() {
+            if held == all_keys {
+                return Some(distance);
+            }
+            if best[&(robots.clone(), held)] < distance {
+                continue;
+            }
+            for (i, place) in robots.iter().enumerate() {
+                for route in &routes[*place] {
+                    let bit = 1 << route.key;
+                    if held & bit != 0 || route.doors & !held != 0 {
+                        continue;
+                    }
+                    let mut next = robots.clone();
+                    next[i] = route.key;
+                    let state = (next, held | bit);
+                    let distance = distance + route.distance;
+                    if best.get(&state).is_none_or(|d| distance < *d) {
+                        best.insert(state.clone(), distance);
+                        heap.push(Reverse((distance, state.0, state.1)));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn part1(input: &[String]) -> usize {
+    Vault::parse(input).shortest_path().unwrap()
+}
+
+pub fn part2(input: &[String]) -> usize {
+    let mut vault = Vault::parse(input);
+    vault.split_entrance();
+    vault.shortest_path().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vault(text: &str) -> Vault {
+        let lines: Vec<String> =
+            text.lines().map(|l| l.trim().to_string()).collect();
+        Vault::parse(&lines)
+    }
+
+    #[test]
+    fn test_routes() {
+        let vault = vault(
+            "
+            #########
+            #b.A.@.a#
+            #########",
+        );
+        let mut routes = vault.routes(vault.entrances[0]);
+        routes.sort_by_key(|r| r.key);
+        assert_eq!(
+            routes,
+            vec![
+                Route {
+                    key: 0,
+                    distance: 2,
+                    doors: 0
+                },
+                Route {
+                    key: 1,
+                    distance: 4,
+                    doors: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_part1() {
+        let examples = [
+            (
+                "
+                #########
+                #b.A.@.a#
+                #########",
+                8,
+            ),
+            (
+                "
+                ########################
+                #f.D.E.e.C.b.A.@.a.B.c.#
+                ######################.#
+                #d.....................#
+                ########################",
+                86,
+            ),
+            (
+                "
+                ########################
+                #...............b.C.D.f#
+                #.######################
+                #.....@.a.B.c.d.A.e.F.g#
+                ########################",
+                132,
+            ),
+            (
+                "
+                #################
+                #i.G..c...e..H.p#
+                ########.########
+                #j.A..b...f..D.o#
+                ########@########
+                #k.E..a...g..B.n#
+                ########.########
+                #l.F..d...h..C.m#
+                #################",
+                136,
+            ),
+            (
+                "
+                ########################
+                #@..............ac.GI.b#
+                ###d#e#f################
+                ###A#B#C################
+                ###g#h#i################
+                ########################",
+                81,
+            ),
+        ];
+        for (text, steps) in examples.iter() {
+            assert_eq!(vault(text).shortest_path(), Some(*steps));
+        }
+    }
+
+    #[test]
+    fn test_part2() {
+        let mut split = vault(
+            "
+            #######
+            #a.#Cd#
+            ##...##
+            ##.@.##
+            ##...##
+            #cB#Ab#
+            #######",
+        );
+        split.split_entrance();
+        assert_eq!(split.entrances.len(), 4);
+        assert_eq!(split.shortest_path(), Some(8));
+
+        let examples = [
+            (
+                "
+                ###############
+                #d.ABC.#.....a#
+                ######@#@######
+                ###############
+                ######@#@######
+                #b.....#.....c#
+                ###############",
+                24,
+            ),
+            (
+                "
+                #############
+                #DcBa.#.GhKl#
+                #.###@#@#I###
+                #e#d#####j#k#
+                ###C#@#@###J#
+                #fEbA.#.FgHi#
+                #############",
+                32,
+            ),
+            (
+                "
+                #############
+                #g#f.D#..h#l#
+                #F###e#E###.#
+                #dCba@#@BcIJ#
+                #############
+                #nK.L@#@G...#
+                #M###N#H###.#
+                #o#m..#i#jk.#
+                #############",
+                72,
+            ),
+        ];
+        for (text, steps) in examples.iter() {
+            assert_eq!(vault(text).shortest_path(), Some(*steps));
+        }
+    }
+}
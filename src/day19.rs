@@ -1,33 +1,26 @@
-extern crate ncurses;
-
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 
-use crate::computer::{run, ProgramState};
-
-use ncurses::*;
+use crate::computer::ProgramState;
+use crate::grid::Point;
+use crate::render::Renderer;
 
 pub enum Tile {
     Open,
     Beam,
 }
 
-#[allow(dead_code)]
-pub fn render(map: HashMap<(i32, i32), Tile>) {
-    initscr();
-    curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-    clear();
-    for ((x, y), tile) in map {
-        match tile {
-            Tile::Open => mvprintw(y, x, "."),
-            Tile::Beam => mvprintw(y, x, "#"),
+pub fn render(map: &HashMap<(i32, i32), Tile>, renderer: &mut dyn Renderer) {
+    renderer.clear();
+    for (point, tile) in map {
+        let text = match tile {
+            Tile::Open => ".",
+            Tile::Beam => "#",
         };
+        renderer.draw(Point::from(*point), text);
     }
-    refresh();
-    curs_set(CURSOR_VISIBILITY::CURSOR_VISIBLE);
-    getch();
-    endwin();
+    renderer.present();
 }
 
 pub fn load_input(name: &str) -> Vec<i64> {
@@ -43,60 +36,38 @@ pub fn load_input(name: &str) -> Vec<i64> {
     output
 }
 
-pub fn part1(input: &Vec<i64>) -> i64 {
+/// What the drone sees at every point of a `size` by `size` square from the
+/// emitter.
+pub fn scan(input: &Vec<i64>, size: i32) -> HashMap<(i32, i32), Tile> {
     let mut map = HashMap::new();
-
-    let mut sum = 0;
-    for x in 0..50 {
-        for y in 0..50 {
-            let mut state = ProgramState::new(input);
-            run(x as i64, &mut state);
-            if let Some(value) = run(y as i64, &mut state) {
-                if value == 1 {
-                    sum += 1;
-                    map.insert((x, y), Tile::Beam);
-                } else {
-                    map.insert((x, y), Tile::Open);
-                }
+    for x in 0..size {
+        for y in 0..size {
+            if check(&(x, y), input) {
+                map.insert((x, y), Tile::Beam);
+            } else {
+                map.insert((x, y), Tile::Open);
             }
         }
     }
+    map
+}
 
-    //render(map);
-
-    sum
+pub fn part1(input: &Vec<i64>) -> i64 {
+    scan(input, 50)
+        .values()
+        .filter(|tile| matches!(tile, Tile::Beam))
+        .count() as i64
 }
 
 pub fn check(pt: &(i32, i32), input: &Vec<i64>) -> bool {
     let mut state = ProgramState::new(input);
-    run(pt.0 as i64, &mut state);
-    if let Some(value) = run(pt.1 as i64, &mut state) {
-        if value == 1 {
-            true
-        } else {
-            false
-        }
-    } else {
-        false
-    }
+    state.push_inputs(vec![pt.0 as i64, pt.1 as i64]);
+    state.run_until_blocked();
+    state.drain_output() == vec![1]
 }
 
 pub fn part2(input: &Vec<i64>) -> i64 {
-    let mut map = HashMap::new();
-
-    for x in 0..10 {
-        for y in 0..10 {
-            let mut state = ProgramState::new(input);
-            run(x as i64, &mut state);
-            if let Some(value) = run(y as i64, &mut state) {
-                if value == 1 {
-                    map.insert((x, y), Tile::Beam);
-                } else {
-                    map.insert((x, y), Tile::Open);
-                }
-            }
-        }
-    }
+    let map = scan(input, 10);
 
     let mut bpx = 0;
     let mut bpy = 0;
@@ -145,3 +116,23 @@ pub fn part2(input: &Vec<i64>) -> i64 {
     let upper_left = (point.0, point.1 - 99);
     (upper_left.0 * 10000 + upper_left.1) as i64
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::FrameBuffer;
+
+    #[test]
+    fn test_render() {
+        let mut map = HashMap::new();
+        for x in 0..3 {
+            for y in 0..2 {
+                let tile = if x == y { Tile::Beam } else { Tile::Open };
+                map.insert((x, y), tile);
+            }
+        }
+        let mut renderer = FrameBuffer::new();
+        render(&map, &mut renderer);
+        assert_eq!(renderer.frames, vec!["#..\n.#."]);
+    }
+}
@@ -1,21 +1,13 @@
-extern crate ncurses;
-
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::sync::mpsc;
+use std::thread;
 use std::thread::JoinHandle;
-use std::{thread, time};
-
-use ncurses::*;
 
-#[derive(Debug, Clone)]
-pub enum Move {
-    North = 1,
-    South,
-    West,
-    East,
-}
+use crate::grid::{Bounds, Point};
+use crate::pathfind;
+use crate::render::{Color, Key, Renderer};
 
 #[derive(Debug, Clone)]
 pub enum TileType {
@@ -37,84 +29,54 @@ impl Tile {
     pub fn new(tile_type: TileType, point: (i32, i32)) -> Tile {
         Tile { tile_type, point }
     }
-
-    pub fn neighboring_tiles(&self) -> Vec<(i32, i32, Move)> {
-        let pt = self.point;
-        let npt = (pt.0, pt.1 - 1, Move::North);
-        let spt = (pt.0, pt.1 + 1, Move::South);
-        let ept = (pt.0 + 1, pt.1, Move::East);
-        let wpt = (pt.0 - 1, pt.1, Move::West);
-
-        vec![npt, spt, ept, wpt]
-    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DoorType {
     Inner,
     Outer,
 }
 
+// A portal as seen from one side.  `point` is the floor tile in front of
+// it.
 #[derive(Debug, Clone)]
 pub struct Door {
     name: String,
-    level: u32,
     door_type: DoorType,
     point: (i32, i32),
-    exit: (i32, i32),
 }
 
 impl Door {
-    pub fn new(
-        name: String,
-        level: u32,
-        door_type: DoorType,
-        point: (i32, i32),
-        exit: (i32, i32),
-    ) -> Door {
+    pub fn new(name: String, door_type: DoorType, point: (i32, i32)) -> Door {
         Door {
             name,
-            level,
             door_type,
             point,
-            exit,
         }
     }
-}
 
-pub fn neighboring_grid_points(pt: &(i32, i32)) -> Vec<(i32, i32, Move)> {
-    let npt = (pt.0, pt.1 - 1, Move::North);
-    let spt = (pt.0, pt.1 + 1, Move::South);
-    let ept = (pt.0 + 1, pt.1, Move::East);
-    let wpt = (pt.0 - 1, pt.1, Move::West);
+    // Inner doors lead one level deeper, outer ones one level back out
+    pub fn level_change(&self) -> i32 {
+        match self.door_type {
+            DoorType::Inner => 1,
+            DoorType::Outer => -1,
+        }
+    }
+}
 
-    vec![npt, spt, ept, wpt]
+pub fn neighboring_grid_points(pt: &(i32, i32)) -> Vec<(i32, i32)> {
+    Point::from(*pt).neighbours().map(|p| p.into()).collect()
 }
 
+// Stepping onto a portal comes out of its partner instead
 pub fn neighboring_grid_points_with_portals(
     pt: &(i32, i32),
     portals: &HashMap<(i32, i32), (i32, i32)>,
-) -> Vec<(i32, i32, Move)> {
-    let mut npt = (pt.0, pt.1 - 1, Move::North);
-    let mut spt = (pt.0, pt.1 + 1, Move::South);
-    let mut ept = (pt.0 + 1, pt.1, Move::East);
-    let mut wpt = (pt.0 - 1, pt.1, Move::West);
-
-    // Handle portals
-    if let Some(portal_pt) = portals.get(&(npt.0, npt.1)) {
-        npt = (portal_pt.0, portal_pt.1, npt.2);
-    }
-    if let Some(portal_pt) = portals.get(&(spt.0, spt.1)) {
-        spt = (portal_pt.0, portal_pt.1, spt.2);
-    }
-    if let Some(portal_pt) = portals.get(&(ept.0, ept.1)) {
-        ept = (portal_pt.0, portal_pt.1, ept.2);
-    }
-    if let Some(portal_pt) = portals.get(&(wpt.0, wpt.1)) {
-        wpt = (portal_pt.0, portal_pt.1, wpt.2);
-    }
-
-    vec![npt, spt, ept, wpt]
+) -> Vec<(i32, i32)> {
+    neighboring_grid_points(pt)
+        .into_iter()
+        .map(|new_pt| *portals.get(&new_pt).unwrap_or(&new_pt))
+        .collect()
 }
 
 pub fn load_input(name: &str) -> HashMap<(i32, i32), Tile> {
@@ -152,229 +114,131 @@ pub fn load_input(name: &str) -> HashMap<(i32, i32), Tile> {
     output
 }
 
-pub fn start_render_thread() -> (
-    mpsc::Sender<Option<HashMap<(i32, i32), TileType>>>,
-    JoinHandle<()>,
-) {
-    let (tx, rx) = mpsc::channel();
+/// One picture of the maze for the render thread.
+pub type Frame = HashMap<(i32, i32), TileType>;
 
-    let handle: thread::JoinHandle<()> = thread::spawn(move || {
-        let window = initscr();
-        start_color();
-        init_pair(1, COLOR_WHITE, COLOR_BLACK);
-        init_pair(2, COLOR_BLUE, COLOR_BLACK);
-        init_pair(3, COLOR_GREEN, COLOR_BLACK);
-        init_pair(4, COLOR_RED, COLOR_BLACK);
-
-        curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-        noecho();
-        nodelay(window, true);
-        keypad(stdscr(), true);
+/// Draws every map sent until `None` arrives, on a thread of its own, and
+/// hands the renderer back when joined.  The up and down keys scroll, for
+/// mazes taller than the terminal.
+pub fn start_render_thread<R: Renderer + Send + 'static>(
+    mut renderer: R,
+) -> (mpsc::Sender<Option<Frame>>, JoinHandle<R>) {
+    let (tx, rx) = mpsc::channel();
 
+    let handle: thread::JoinHandle<R> = thread::spawn(move || {
         // Offset for display height
         let mut offset = 0;
 
         loop {
-            // Handle input if button pressed (non-blocking getch call)
-            let c = getch();
-            if c == KEY_DOWN {
-                offset += 1;
-                clear();
-            } else if c == KEY_UP {
-                offset -= 1;
-                clear();
+            match renderer.poll_key() {
+                Some(Key::Down) => offset += 1,
+                Some(Key::Up) => offset -= 1,
+                None => (),
             }
 
             // Handle message if one available
             if let Some(map) = rx.recv().unwrap() {
-                render(&map, &offset);
+                render(&map, offset, &mut renderer);
             } else {
                 break;
             }
         }
-
-        clear();
-        curs_set(CURSOR_VISIBILITY::CURSOR_VISIBLE);
-        refresh();
-        endwin();
+        renderer
     });
 
     (tx, handle)
 }
 
-#[allow(dead_code)]
-pub fn render(map: &HashMap<(i32, i32), TileType>, offset: &i32) {
-    let mut min_x = 0;
-    let min_y = offset;
-    for (x, _y) in map.keys() {
-        if *x < min_x {
-            min_x = *x;
-        }
-    }
+pub fn render(map: &Frame, offset: i32, renderer: &mut dyn Renderer) {
+    let bounds = Bounds::of(map.keys().map(|pt| Point::from(*pt)));
+    let min =
+        Point::new(bounds.map_or(0, |bounds| bounds.min.x.min(0)), offset);
 
-    for ((x, y), tile) in map {
-        attron(COLOR_PAIR(1));
+    renderer.clear();
+    for (point, tile) in map {
+        let point = Point::from(*point) - min;
         match tile {
-            TileType::Start => {
-                attron(COLOR_PAIR(3));
-                let out = mvprintw(y - min_y, x - min_x, "S");
-                attroff(COLOR_PAIR(3));
-                out
-            }
-            TileType::End => {
-                attron(COLOR_PAIR(4));
-                let out = mvprintw(y - min_y, x - min_x, "E");
-                attron(COLOR_PAIR(4));
-                out
-            }
-            TileType::Floor => mvprintw(y - min_y, x - min_x, "."),
-            TileType::Water => {
-                attron(COLOR_PAIR(2));
-                let out = mvprintw(y - min_y, x - min_x, "~");
-                attroff(COLOR_PAIR(2));
-                out
-            }
-            TileType::Wall => mvprintw(y - min_y, x - min_x, "#"),
+            TileType::Start => renderer.draw_colored(point, "S", Color::Green),
+            TileType::End => renderer.draw_colored(point, "E", Color::Red),
+            TileType::Floor => renderer.draw_colored(point, ".", Color::White),
+            TileType::Water => renderer.draw_colored(point, "~", Color::Blue),
+            TileType::Wall => renderer.draw_colored(point, "#", Color::White),
             TileType::Portal(c) => {
-                mvprintw(y - min_y, x - min_x, &c.to_string())
+                renderer.draw_colored(point, c, Color::White)
             }
         };
-        attroff(COLOR_PAIR(1));
     }
-
-    refresh();
-    thread::sleep(time::Duration::from_millis(33));
+    renderer.present();
 }
 
-pub fn path(
-    p1: &(i32, i32),
-    p2: &(i32, i32),
+// Spreads water out from the start, through portals, until it reaches the end
+fn flood(
     map: &HashMap<(i32, i32), Tile>,
-) -> Option<u32> {
-    let mut cloud: HashMap<(i32, i32), u32> = HashMap::new();
-    cloud.insert(*p1, 0);
-
-    loop {
-        // Find new_points
-        let mut new_points = HashMap::new();
-        for (point, path) in &cloud {
-            for tup in neighboring_grid_points(point) {
-                let new_pt = (tup.0, tup.1);
-                let new_path = path + 1;
-                if !cloud.contains_key(&new_pt) {
-                    if let Some(tile) = map.get(&new_pt) {
-                        match tile.tile_type {
-                            TileType::Floor => {
-                                new_points.insert(new_pt, new_path);
-                            }
-                            _ => (),
-                        }
-                    }
-                }
-            }
-        }
-
-        // Break if no new points
-        if new_points.len() == 0 {
-            break;
-        }
-
-        // Insert new_points into cloud
-        for (point, path) in &new_points {
-            if let Some(tile) = map.get(point) {
-                match tile.tile_type {
-                    TileType::Wall => continue,
-                    TileType::Portal(_) => continue,
-                    _ => (),
-                }
-            }
-
-            if let Some(existing_path) = cloud.get_mut(point) {
-                if path < existing_path {
-                    *existing_path = *path;
-                }
-            } else {
-                cloud.insert(point.clone(), path.clone());
-            }
-        }
-
-        // Break if we found a path to p2
-        if cloud.contains_key(p2) {
-            break;
-        }
-    }
-
-    if let Some(value) = cloud.get(p2) {
-        Some(value.clone())
-    } else {
-        None
-    }
+    portals: &HashMap<(i32, i32), (i32, i32)>,
+    start_pt: &(i32, i32),
+    end_pt: &(i32, i32),
+) -> pathfind::Search<(i32, i32), usize> {
+    pathfind::bfs(
+        *start_pt,
+        |point| {
+            neighboring_grid_points_with_portals(point, portals)
+                .into_iter()
+                .filter(|new_pt| match map.get(new_pt) {
+                    Some(tile) => matches!(
+                        tile.tile_type,
+                        TileType::Floor | TileType::End
+                    ),
+                    None => false,
+                })
+                .collect::<Vec<_>>()
+        },
+        |point| point == end_pt,
+    )
 }
 
 pub fn water_filling(
-    map: &mut HashMap<(i32, i32), Tile>,
+    map: &HashMap<(i32, i32), Tile>,
     portals: &HashMap<(i32, i32), (i32, i32)>,
     start_pt: &(i32, i32),
     end_pt: &(i32, i32),
 ) -> i64 {
-    //let (tx, handle) = start_render_thread();
-
-    let mut cloud: HashMap<(i32, i32), Vec<Move>> = HashMap::new();
-    cloud.insert(*start_pt, vec![]);
-
-    let mut t = 1;
-    loop {
-        // Find new_points
-        let mut new_points = HashMap::new();
-        for (point, path) in &cloud {
-            for tup in neighboring_grid_points_with_portals(point, portals) {
-                let new_pt = (tup.0, tup.1);
-                let mut new_path = path.clone();
-                new_path.push(tup.2);
-                if !cloud.contains_key(&new_pt) {
-                    new_points.insert(new_pt, new_path);
-                }
-            }
-        }
-
-        // Insert new_points into cloud
-        for (point, path) in &new_points {
-            if let Some(tile) = &map.get(point) {
-                match tile.tile_type {
-                    TileType::Wall => continue,
-                    TileType::Portal(_) => continue,
-                    _ => (),
-                }
-            }
-
-            map.insert(*point, Tile::new(TileType::Water, *point));
-
-            if let Some(existing_path) = cloud.get_mut(point) {
-                if path.len() < existing_path.len() {
-                    *existing_path = (*path).clone();
-                }
-            } else {
-                cloud.insert(point.clone(), path.clone());
-            }
-        }
-
-        //tx.send(Some(map.clone())).unwrap();
+    flood(map, portals, start_pt, end_pt)
+        .goal_distance()
+        .expect("No path from AA to ZZ") as i64
+}
 
-        // See if end_pt is in cloud yet
-        let mut done = false;
-        if cloud.contains_key(end_pt) {
-            done = true;
+/// Shows the water spreading from AA until it reaches ZZ, a step a frame,
+/// on a render thread.  Returns the renderer once the last frame is drawn.
+pub fn animate<R: Renderer + Send + 'static>(
+    input: &HashMap<(i32, i32), Tile>,
+    renderer: R,
+) -> R {
+    let maze = mark_ends(input);
+    let search = flood(&maze.map, &maze.portals, &maze.start, &maze.end);
+    let steps = search.goal_distance().expect("No path from AA to ZZ");
+
+    // The points the water reaches at each step, leaving AA and ZZ showing
+    let mut reached = vec![vec![]; steps + 1];
+    for (point, distance) in &search.distances {
+        if *distance <= steps && *point != maze.start && *point != maze.end {
+            reached[*distance].push(*point);
         }
+    }
 
-        if done {
-            //tx.send(None).unwrap();
-            //handle.join().unwrap();
-            break;
+    let mut frame: Frame = maze
+        .map
+        .iter()
+        .map(|(point, tile)| (*point, tile.tile_type.clone()))
+        .collect();
+    let (tx, handle) = start_render_thread(renderer);
+    for points in reached {
+        for point in points {
+            frame.insert(point, TileType::Water);
         }
-
-        t += 1;
+        tx.send(Some(frame.clone())).unwrap();
     }
-    t
+    tx.send(None).unwrap();
+    handle.join().unwrap()
 }
 
 pub fn find_portals(
@@ -383,7 +247,7 @@ pub fn find_portals(
     (i32, i32),
     (i32, i32),
     HashMap<(i32, i32), (i32, i32)>,
-    HashMap<String, (i32, i32)>,
+    HashMap<String, Door>,
 ) {
     let mut portals: HashMap<String, Vec<((i32, i32), (i32, i32))>> =
         HashMap::new();
@@ -491,161 +355,216 @@ pub fn find_portals(
         }
     }
 
+    // Outer doors sit on the outside edge of the maze's walls, inner ones
+    // around the hole in the middle
+    let walls = Bounds::of(
+        input
+            .iter()
+            .filter(|(_, tile)| matches!(tile.tile_type, TileType::Wall))
+            .map(|(pt, _)| Point::from(*pt)),
+    )
+    .expect("Maze has no walls");
+    let door_type = |pt: (i32, i32)| {
+        let (min, max) = (walls.min, walls.max);
+        if pt.0 == min.x || pt.0 == max.x || pt.1 == min.y || pt.1 == max.y {
+            DoorType::Outer
+        } else {
+            DoorType::Inner
+        }
+    };
+
     let mut start_pt = (0, 0);
     let mut end_pt = (0, 0);
-    let mut portal_names = HashMap::new();
+    let mut doors = HashMap::new();
     let mut output = HashMap::new();
     for (name, v) in portals {
-        if name == "AA" {
-            start_pt = v[0].1;
-            portal_names.insert(name.clone(), v[0].1);
-        } else if name == "ZZ" {
-            end_pt = v[0].1;
-            portal_names.insert(name.clone(), v[0].1);
+        if name == "AA" || name == "ZZ" {
+            let pt = v[0].1;
+            if name == "AA" {
+                start_pt = pt;
+            } else {
+                end_pt = pt;
+            }
+            let door = Door::new(name.clone(), DoorType::Outer, pt);
+            doors.insert(name, door);
         } else {
             let pt1 = v[0];
             let pt2 = v[1];
             let name_a = format!("{}{}", name, "A");
             let name_b = format!("{}{}", name, "B");
-            portal_names.insert(name_a, pt1.1);
-            portal_names.insert(name_b, pt2.1);
+            let door_a = Door::new(name.clone(), door_type(pt1.1), pt1.1);
+            let door_b = Door::new(name.clone(), door_type(pt2.1), pt2.1);
+            doors.insert(name_a, door_a);
+            doors.insert(name_b, door_b);
             output.insert(pt1.0, pt2.1);
             output.insert(pt2.0, pt1.1);
         }
     }
 
-    (start_pt, end_pt, output, portal_names)
+    (start_pt, end_pt, output, doors)
 }
 
+// Returns a HashMap where the key is a door's name, and the value is a HashMap
+// of the doors that can be walked to from it without using any portals, along
+// with the distance to each.
 pub fn explore_segments(
     map: &HashMap<(i32, i32), Tile>,
-    name_map: &HashMap<String, (i32, i32)>,
-    start_pt: &(i32, i32),
-    end_pt: &(i32, i32),
+    doors: &HashMap<String, Door>,
 ) -> HashMap<String, HashMap<String, u32>> {
     let mut output: HashMap<String, HashMap<String, u32>> = HashMap::new();
 
-    for name1 in name_map.keys() {
-        let point1 = name_map.get(name1).unwrap();
-
-        for name2 in name_map.keys() {
-            let point2 = name_map.get(name2).unwrap();
-
-            if let Some(dist) = path(&point1, &point2, map) {
-                // If some path found from point1 to point2 sans portals
-                if let Some(valuemap) = output.get_mut(name1) {
-                    valuemap.insert((*name2).clone(), dist);
-                } else {
-                    let mut new_map = HashMap::new();
-                    new_map.insert((*name2).clone(), dist);
-                    output.insert((*name1).clone(), new_map);
-                }
+    for (name1, door1) in doors {
+        let reachable = pathfind::bfs(
+            door1.point,
+            |point| {
+                neighboring_grid_points(point)
+                    .into_iter()
+                    .filter(|new_pt| match map.get(new_pt) {
+                        Some(tile) => matches!(tile.tile_type, TileType::Floor),
+                        None => false,
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |_| false,
+        );
+        let mut dests = HashMap::new();
+        for (name2, door2) in doors {
+            if name1 == name2 {
+                continue;
+            }
+            if let Some(dist) = reachable.distance(&door2.point) {
+                dests.insert(name2.clone(), dist as u32);
             }
         }
+        output.insert(name1.clone(), dests);
     }
 
-    // Output a HashMap where the key is the starting points String name, and
-    // the value is a HashMap of the portals that can be reached from that
-    // starting point.  The value HashMaps key is the String name of the
-    // destination, and the value HashMaps value is the distance between the
-    // two points.
     output
 }
 
-pub fn part1(input: &HashMap<(i32, i32), Tile>) -> i64 {
+// A copy of the maze with AA and ZZ marked, their points, and the portals
+struct MarkedMaze {
+    map: HashMap<(i32, i32), Tile>,
+    start: (i32, i32),
+    end: (i32, i32),
+    portals: HashMap<(i32, i32), (i32, i32)>,
+}
+
+fn mark_ends(input: &HashMap<(i32, i32), Tile>) -> MarkedMaze {
     let mut map: HashMap<(i32, i32), Tile> = (*input).clone();
-    let (start_pt, end_pt, portals, _names) = find_portals(&map);
+    let (start_pt, end_pt, portals, _doors) = find_portals(&map);
 
     // Populate start and stop tiles
     map.insert(start_pt, Tile::new(TileType::Start, start_pt));
     map.insert(end_pt, Tile::new(TileType::End, end_pt));
+    MarkedMaze {
+        map,
+        start: start_pt,
+        end: end_pt,
+        portals,
+    }
+}
 
-    let out = water_filling(&mut map, &portals, &start_pt, &end_pt);
-    out
+pub fn part1(input: &HashMap<(i32, i32), Tile>) -> i64 {
+    let maze = mark_ends(input);
+    water_filling(&maze.map, &maze.portals, &maze.start, &maze.end)
 }
 
-// For this part most likely water filling alone won't be fast enough.
-//
-// I'm thinking of using a map that is keyed off of the portal entrances, and
-// the value would be a set of the portals that can be reached from the keyed
-// portal, along with a number of steps to each of them in a tuple.  Like this:
-//
-//          Keyed Portal        (Other Portal, Dist. to Other)
-// HashMap<(String, Char), HashSet<((String, Char), u32)>>
+// The maze is recursive, so every inner door leads to a copy of the maze one
+// level down, and every outer door back up a level.  At the outermost level
+// the outer doors are walls, and AA and ZZ only exist there.
 //
-// The portals are represented by a tuple of the portal string, and then a char
-// designating either that it is the inner or the outer edge portal.
-//
-// When creating the sets we need to take care that the start and end are only
-// counted as portals on the outermost (1st) level of the maze.
-//
-// We can use water_filling with modified rules (don't traverse portals) to
-// distances between connected portals, and which portals are connected at all.
-// Then we create our dataset and traverse the graph it creates, keeping track
-// of each of the states (portals) and the shortest path from the start to that
-// state.  Once we've gotten to all of them we simply look at the path to the
-// end portal. (Something sort of like the Viterbi Algorithm?)
+// We walk the graph of distances between doors from explore_segments with
+// Dijkstra's algorithm, the state being which door we've just come through
+// and the level we're on.
 pub fn part2(input: &HashMap<(i32, i32), Tile>) -> u32 {
-    let mut map = input.clone();
-    let (start_pt, end_pt, _portals, names) = find_portals(&map);
-
-    // Populate start and stop tiles
-    map.insert(start_pt, Tile::new(TileType::Start, start_pt));
-    map.insert(end_pt, Tile::new(TileType::End, end_pt));
-
-    let seg_map = explore_segments(&mut map, &names, &start_pt, &end_pt);
-    println!("Finished exploring");
-
-    // Viterbi(-like) algorithm here
-    let mut dist_map = HashMap::new();
-    let dests = seg_map.get("AA").unwrap();
-    for (dpt, dist) in dests {
-        dist_map.insert(dpt.clone(), *dist);
+    let (_start_pt, _end_pt, _portals, doors) = find_portals(input);
+    let seg_map = explore_segments(input, &doors);
+
+    // The door on the other side of each portal
+    let mut partners = HashMap::new();
+    for (name, door) in &doors {
+        for (other, other_door) in &doors {
+            if other != name && other_door.name == door.name {
+                partners.insert(name.clone(), other.clone());
+            }
+        }
     }
 
-    let mut jumps = 0;
-    loop {
-        println!("jumps: {}", jumps);
-        let old_dist_map = dist_map.clone();
-        for (dest, dest_dist) in dist_map.clone() {
-            println!("dest: {}", dest);
-
-            // Get the other door
-            let preamble = dest.chars().take(2).collect::<String>();
-
-            let mut other = "A";
-            if preamble != "AA" && preamble != "ZZ" {
-                let current_letter = dest.as_bytes()[2] as char;
-                match current_letter {
-                    'A' => other = "B",
-                    'B' => other = "A",
-                    _ => continue,
+    // Deeper than one level per door can't be part of a shortest path
+    let max_level = doors.len() as i32;
+
+    let neighbours = |(name, level): &(String, i32)| {
+        let mut output = vec![];
+        for dest in seg_map[name].keys() {
+            match dest.as_str() {
+                "AA" => (),
+                "ZZ" => {
+                    if *level == 0 {
+                        output.push((dest.clone(), 0));
+                    }
                 }
-            } else {
-                continue;
-            }
-
-            let other = format!("{}{}", preamble, other.to_string());
-            println!("other: {}", other);
-            let new_dests_map =
-                seg_map.get(&other).expect("seg_map should have other");
-
-            for (new_dest, new_dist) in new_dests_map {
-                println!("new_dests_map: {:?}", new_dests_map);
-                if !dist_map.contains_key(new_dest) {
-                    dist_map.insert(new_dest.clone(), dest_dist + new_dist);
+                _ => {
+                    let next_level = level + doors[dest].level_change();
+                    if next_level >= 0 && next_level <= max_level {
+                        output.push((partners[dest].clone(), next_level));
+                    }
                 }
             }
         }
+        output
+    };
 
-        if old_dist_map.len() == dist_map.len() {
-            break;
+    // Walking to the door, plus a step through the portal unless it's ZZ
+    let cost = |(from, _): &(String, i32), (to, _): &(String, i32)| {
+        if to == "ZZ" {
+            seg_map[from][to]
+        } else {
+            seg_map[from][&partners[to]] + 1
         }
-        jumps += 1;
+    };
+
+    let search = pathfind::dijkstra(
+        ("AA".to_string(), 0),
+        neighbours,
+        cost,
+        |(name, _)| name == "ZZ",
+    );
+    search.goal_distance().expect("No path from AA to ZZ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::render::FrameBuffer;
+
+    #[test]
+    fn test_part1() {
+        let input = load_input("inputs/20a.txt");
+        assert_eq!(part1(&input), 23);
     }
 
-    println!("{:?}", dist_map);
-    *dist_map
-        .get("ZZ")
-        .expect("Final result should be in dist_map")
+    #[test]
+    fn test_animate() {
+        let input = load_input("inputs/20a.txt");
+        let renderer = animate(&input, FrameBuffer::new());
+
+        // One frame before the water moves, then one for each step to ZZ
+        assert_eq!(renderer.frames.len(), 24);
+        let first = &renderer.frames[0];
+        let last = renderer.frames.last().unwrap();
+        assert_eq!(first.lines().nth(2), Some("  #######S#########"));
+        assert_eq!(first.lines().nth(16), Some("  ###########E#####"));
+        assert!(!first.contains('~'));
+        assert_eq!(last.lines().nth(3), Some("  #######~~~~~~~~~#"));
+        assert!(!last.contains('.'));
+    }
+
+    #[test]
+    fn test_part2() {
+        let input = load_input("inputs/20a.txt");
+        assert_eq!(part2(&input), 26);
+        let input = load_input("inputs/20b.txt");
+        assert_eq!(part2(&input), 396);
+    }
 }
@@ -0,0 +1,464 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::ascii::AsciiMachine;
+use crate::computer::load_program;
+
+pub const MAX_INSTRUCTIONS: usize = 15;
+const JUMP_LENGTH: usize = 4;
+
+pub fn load_input(name: &str) -> Vec<i64> {
+    load_program(name)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ScriptError {}
+
+fn error(line: usize, message: String) -> ScriptError {
+    ScriptError { line, message }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    /// Number of hull sensors readable in this mode, starting from A.
+    pub fn sensors(self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+}
+
+/// Sensors `A` to `I` see the hull 1 to 9 tiles ahead.  `T` and `J` are
+/// the writable temporary and jump registers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Register {
+    Sensor(usize),
+    T,
+    J,
+}
+
+impl Register {
+    fn parse(text: &str) -> Option<Register> {
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some('T'), None) => Some(Register::T),
+            (Some('J'), None) => Some(Register::J),
+            (Some(c @ 'A'..='I'), None) => {
+                Some(Register::Sensor(c as usize - 'A' as usize))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::Sensor(i) => write!(f, "{}", (b'A' + *i as u8) as char),
+            Register::T => write!(f, "T"),
+            Register::J => write!(f, "J"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    pub op: Op,
+    pub x: Register,
+    pub y: Register,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.op {
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Not => "NOT",
+        };
+        write!(f, "{} {} {}", op, self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+    pub instructions: Vec<Instruction>,
+    pub mode: Mode,
+}
+
+impl Script {
+    /// Parses one instruction a line, finishing with `WALK` or `RUN`.
+    /// Blank lines are skipped.
+    pub fn parse(text: &str) -> Result<Script, ScriptError> {
+        let mut instructions = vec![];
+        // The source line of each instruction, for reporting errors
+        let mut sources = vec![];
+        let lines: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .collect();
+
+        for (n, (line, text)) in lines.iter().enumerate() {
+            let line = *line;
+            let words: Vec<&str> = text.split_whitespace().collect();
+            let op = match words[0] {
+                "AND" => Op::And,
+                "OR" => Op::Or,
+                "NOT" => Op::Not,
+                "WALK" | "RUN" if words.len() == 1 => {
+                    if let Some((line, _)) = lines.get(n + 1) {
+                        return Err(error(
+                            *line,
+                            format!("Nothing may follow {}", words[0]),
+                        ));
+                    }
+                    let mode = if words[0] == "WALK" {
+                        Mode::Walk
+                    } else {
+                        Mode::Run
+                    };
+                    let script = Script { instructions, mode };
+                    // Point at the source line, not the instruction number
+                    script
+                        .validate()
+                        .map_err(|e| error(sources[e.line - 1], e.message))?;
+                    return Ok(script);
+                }
+                _ => {
+                    return Err(error(
+                        line,
+                        format!("Unknown op {:?}", words[0]),
+                    ))
+                }
+            };
+            if words.len() != 3 {
+                return Err(error(line, "Expected two registers".to_string()));
+            }
+            let register = |name: &str| {
+                Register::parse(name).ok_or_else(|| {
+                    error(line, format!("Unknown register {:?}", name))
+                })
+            };
+            instructions.push(Instruction {
+                op,
+                x: register(words[1])?,
+                y: register(words[2])?,
+            });
+            sources.push(line);
+        }
+        Err(error(0, "Missing WALK or RUN".to_string()))
+    }
+
+    /// Checks the instruction limit, that only `T` and `J` are written and
+    /// that `WALK` scripts only read sensors A to D.  Errors give the
+    /// number of the instruction at fault, counting from 1.
+    pub fn validate(&self) -> Result<(), ScriptError> {
+        if self.instructions.len() > MAX_INSTRUCTIONS {
+            return Err(error(
+                MAX_INSTRUCTIONS + 1,
+                format!("More than {} instructions", MAX_INSTRUCTIONS),
+            ));
+        }
+        for (i, inst) in self.instructions.iter().enumerate() {
+            if let Register::Sensor(_) = inst.y {
+                return Err(error(i + 1, format!("Can't write to {}", inst.y)));
+            }
+            if let Register::Sensor(n) = inst.x {
+                if n >= self.mode.sensors() {
+                    return Err(error(
+                        i + 1,
+                        format!("{} can't be read in this mode", inst.x),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lines to send to the droid, ending with `WALK` or `RUN`.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> =
+            self.instructions.iter().map(|i| i.to_string()).collect();
+        lines.push(match self.mode {
+            Mode::Walk => "WALK".to_string(),
+            Mode::Run => "RUN".to_string(),
+        });
+        lines
+    }
+
+    /// Whether the droid jumps given what its sensors see, ground being
+    /// true.
+    pub fn jumps(&self, sensors: &[bool]) -> bool {
+        let mut t = false;
+        let mut j = false;
+        for inst in &self.instructions {
+            let x = match inst.x {
+                Register::Sensor(i) => sensors[i],
+                Register::T => t,
+                Register::J => j,
+            };
+            let y = match inst.y {
+                Register::T => &mut t,
+                Register::J => &mut j,
+                Register::Sensor(_) => panic!("Can't write to {}", inst.y),
+            };
+            *y = match inst.op {
+                Op::And => x && *y,
+                Op::Or => x || *y,
+                Op::Not => !x,
+            };
+        }
+        j
+    }
+
+    /// Runs the droid across a hull like `#####.#..########` without the
+    /// VM.  Anything past the end counts as ground.  Returns where it fell,
+    /// if it did.
+    pub fn simulate(&self, hull: &str) -> Result<(), usize> {
+        let hull: Vec<bool> = hull.chars().map(|c| c == '#').collect();
+        let ground = |i: usize| *hull.get(i).unwrap_or(&true);
+
+        let mut position = 0;
+        while position < hull.len() {
+            if !ground(position) {
+                return Err(position);
+            }
+            let sensors: Vec<bool> =
+                (1..=9).map(|i| ground(position + i)).collect();
+            position += if self.jumps(&sensors) { JUMP_LENGTH } else { 1 };
+        }
+        Ok(())
+    }
+}
+
+// Steps an odometer of `indices` each below `base`, returning false once it
+// wraps back round to all zeros
+fn next_combination(indices: &mut [usize], base: usize) -> bool {
+    for index in indices.iter_mut() {
+        *index += 1;
+        if *index < base {
+            return true;
+        }
+        *index = 0;
+    }
+    false
+}
+
+/// Brute forces the shortest script of at most `max_len` instructions that
+/// gets across every hull.  The search space grows by a factor of 36 (WALK)
+/// or 66 (RUN) per instruction, so this is only practical for short scripts.
+pub fn search(hulls: &[&str], mode: Mode, max_len: usize) -> Option<Script> {
+    let mut readable: Vec<Register> =
+        (0..mode.sensors()).map(Register::Sensor).collect();
+    readable.push(Register::T);
+    readable.push(Register::J);
+
+    let mut candidates = vec![];
+    for op in &[Op::And, Op::Or, Op::Not] {
+        for x in &readable {
+            for y in &[Register::T, Register::J] {
+                candidates.push(Instruction {
+                    op: *op,
+                    x: *x,
+                    y: *y,
+                });
+            }
+        }
+    }
+
+    for len in 1..=max_len {
+        let mut indices = vec![0; len];
+        loop {
+            let script = Script {
+                instructions: indices.iter().map(|i| candidates[*i]).collect(),
+                mode,
+            };
+            if hulls.iter().all(|hull| script.simulate(hull).is_ok()) {
+                return Some(script);
+            }
+            if !next_combination(&mut indices, candidates.len()) {
+                break;
+            }
+        }
+    }
+    None
+}
+
+/// Pulls the hull out of the droid's report of how it fell.  The first
+/// frame's bottom row is the only line made of nothing but hull tiles.
+pub fn parse_fall(text: &str) -> Option<String> {
+    text.lines()
+        .skip_while(|line| !line.starts_with("Didn't make it across"))
+        .find(|line| {
+            line.contains('#') && line.chars().all(|c| c == '#' || c == '.')
+        })
+        .map(|line| line.to_string())
+}
+
+/// Sends a script to the droid.  Returns the hull damage it reports, or the
+/// hull it fell into.
+pub fn run_script(program: &[i64], script: &Script) -> Result<i64, String> {
+    let mut droid = AsciiMachine::new(program);
+    let lines = script.lines();
+    droid.send_lines(lines.iter().map(|line| line.as_str()));
+    let output = droid.run();
+    match output.answer {
+        Some(damage) => Ok(damage),
+        None => Err(parse_fall(&output.text).unwrap_or(output.text)),
+    }
+}
+
+// Jump if there's a hole in the next three tiles and ground to land on
+const WALK_SCRIPT: &str = "
+    NOT A J
+    NOT B T
+    OR T J
+    NOT C T
+    OR T J
+    AND D J
+    WALK
+";
+
+// As for walking, but only if the droid can also step or jump on after
+// landing
+const RUN_SCRIPT: &str = "
+    NOT A J
+    NOT B T
+    OR T J
+    NOT C T
+    OR T J
+    AND D J
+    NOT E T
+    NOT T T
+    OR H T
+    AND T J
+    RUN
+";
+
+pub fn part1(input: &[i64]) -> i64 {
+    let script = Script::parse(WALK_SCRIPT).unwrap();
+    run_script(input, &script)
+        .unwrap_or_else(|hull| panic!("Droid fell into {}", hull))
+}
+
+pub fn part2(input: &[i64]) -> i64 {
+    let script = Script::parse(RUN_SCRIPT).unwrap();
+    run_script(input, &script)
+        .unwrap_or_else(|hull| panic!("Droid fell into {}", hull))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let script = Script::parse(WALK_SCRIPT).unwrap();
+        assert_eq!(script.mode, Mode::Walk);
+        assert_eq!(script.instructions.len(), 6);
+        assert_eq!(
+            script.instructions[1],
+            Instruction {
+                op: Op::Not,
+                x: Register::Sensor(1),
+                y: Register::T
+            }
+        );
+        assert_eq!(
+            script.lines().join("\n"),
+            WALK_SCRIPT
+                .trim()
+                .lines()
+                .map(|line| line.trim())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        let line = |text: &str| Script::parse(text).unwrap_err().line;
+        assert_eq!(line("NOT A J\nXOR A J\nWALK"), 2);
+        assert_eq!(line("NOT A B\nWALK"), 1);
+        assert_eq!(line("NOT A J\nNOT E J\nWALK"), 2);
+        assert_eq!(line("NOT K J\nRUN"), 1);
+        assert_eq!(line("RUN\nNOT A J"), 2);
+        assert_eq!(line("NOT A J"), 0);
+        assert_eq!(line(&("NOT A J\n".repeat(16) + "WALK")), 16);
+        assert!(Script::parse(&("NOT A J\n".repeat(15) + "RUN")).is_ok());
+
+        // Blank lines count towards the line numbers of every kind of error
+        assert_eq!(line("\nNOT E J\nWALK"), 2);
+        assert_eq!(line("\nNOT A A\n\nWALK"), 2);
+        assert_eq!(
+            line(&("\n".repeat(3) + &"NOT A J\n".repeat(16) + "WALK")),
+            19
+        );
+        let script = Script {
+            instructions: vec![Instruction {
+                op: Op::Not,
+                x: Register::Sensor(4),
+                y: Register::J,
+            }],
+            mode: Mode::Walk,
+        };
+        assert_eq!(script.validate().unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn test_simulate() {
+        let walk = Script::parse(WALK_SCRIPT).unwrap();
+        let run = Script::parse(RUN_SCRIPT).unwrap();
+        for hull in &[
+            "#####.###########",
+            "#####...#########",
+            "#####..#.########",
+        ] {
+            assert_eq!(walk.simulate(hull), Ok(()));
+            assert_eq!(run.simulate(hull), Ok(()));
+        }
+
+        // Walking jumps too early to make the second gap
+        let hull = "#####.#.##..#.####";
+        assert_eq!(walk.simulate(hull), Err(7));
+        assert_eq!(run.simulate(hull), Ok(()));
+
+        let fall = "\nDidn't make it across:\n\n.................\n\
+                    .................\n@................\n\
+                    #####.#.##..#.####\n";
+        assert_eq!(parse_fall(fall), Some(hull.to_string()));
+    }
+
+    #[test]
+    fn test_search() {
+        let script = search(&["#####.###########"], Mode::Walk, 2).unwrap();
+        assert_eq!(script.instructions.len(), 1);
+
+        let hulls = ["#####.###########", "#####..#.########"];
+        let script = search(&hulls, Mode::Walk, 3).unwrap();
+        assert!(hulls.iter().all(|hull| script.simulate(hull).is_ok()));
+        assert_eq!(search(&["####....##"], Mode::Walk, 2), None);
+    }
+}
@@ -0,0 +1,293 @@
+extern crate num;
+
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+use num::bigint::BigInt;
+use num::integer::Integer;
+use num::ToPrimitive;
+
+pub fn load_input(name: &str) -> Vec<String> {
+    let f = BufReader::new(File::open(name).unwrap());
+    f.lines().map(|x| x.unwrap()).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Technique {
+    NewStack,
+    Cut(i64),
+    Increment(i64),
+}
+
+impl Technique {
+    /// Reads one line of the shuffle.  Dealing needs an increment of at
+    /// least one, or cards would pile up on the first space.
+    pub fn parse(text: &str) -> Option<Technique> {
+        let text = text.trim();
+        if text == "deal into new stack" {
+            Some(Technique::NewStack)
+        } else if let Some(n) = text.strip_prefix("cut ") {
+            n.parse().ok().map(Technique::Cut)
+        } else if let Some(n) = text.strip_prefix("deal with increment ") {
+            n.parse().ok().filter(|n| *n > 0).map(Technique::Increment)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn parse_input(input: &[String]) -> Vec<Technique> {
+    input
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            Technique::parse(line)
+                .unwrap_or_else(|| panic!("Unknown technique {:?}", line))
+        })
+        .collect()
+}
+
+/// Shuffles a factory order deck card by card.  Only practical for small
+/// decks, but obviously right, so it's used to check `Shuffle`.
+#[cfg(test)]
+pub fn simulate(techniques: &[Technique], size: usize) -> Vec<usize> {
+    let mut deck: Vec<usize> = (0..size).collect();
+    for technique in techniques {
+        match *technique {
+            Technique::NewStack => deck.reverse(),
+            Technique::Cut(n) => {
+                let n = n.rem_euclid(size as i64) as usize;
+                deck.rotate_left(n);
+            }
+            Technique::Increment(n) => {
+                let mut dealt = vec![0; size];
+                for (i, card) in deck.iter().enumerate() {
+                    dealt[i * n as usize % size] = *card;
+                }
+                deck = dealt;
+            }
+        }
+    }
+    deck
+}
+
+fn modpow(base: i128, exponent: u64, modulus: i128) -> i128 {
+    BigInt::from(base)
+        .modpow(&BigInt::from(exponent), &BigInt::from(modulus))
+        .to_i128()
+        .unwrap()
+}
+
+/// A whole shuffle as the affine map taking a card's position before it to
+/// its position after, `a * x + b` mod the deck size.  Every technique is
+/// one of these, and composing them gives another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shuffle {
+    pub a: i128,
+    pub b: i128,
+    pub size: i128,
+}
+
+impl Shuffle {
+    pub fn identity(size: i64) -> Shuffle {
+        Shuffle {
+            a: 1,
+            b: 0,
+            size: size as i128,
+        }
+    }
+
+    pub fn technique(technique: Technique, size: i64) -> Shuffle {
+        let (a, b) = match technique {
+            Technique::NewStack => (-1, -1),
+            Technique::Cut(n) => (1, -n as i128),
+            Technique::Increment(n) => (n as i128, 0),
+        };
+        Shuffle {
+            a: a.rem_euclid(size as i128),
+            b: b.rem_euclid(size as i128),
+            size: size as i128,
+        }
+    }
+
+    /// The whole of `techniques` on a deck of `size` cards.  Fails on an
+    /// increment sharing a factor with the size, since dealing with it
+    /// would land several cards on the same space.
+    pub fn new(techniques: &[Technique], size: i64) -> Result<Shuffle, String> {
+        let mut shuffle = Shuffle::identity(size);
+        for technique in techniques {
+            if let Technique::Increment(n) = technique {
+                if n.gcd(&size) != 1 {
+                    return Err(format!(
+                        "Can't deal with increment {} on a deck of {}",
+                        n, size
+                    ));
+                }
+            }
+            shuffle = shuffle.then(&Shuffle::technique(*technique, size));
+        }
+        Ok(shuffle)
+    }
+
+    /// This shuffle followed by `other`.
+    pub fn then(&self, other: &Shuffle) -> Shuffle {
+        assert_eq!(self.size, other.size);
+        Shuffle {
+            a: other.a * self.a % self.size,
+            b: (other.a * self.b + other.b) % self.size,
+            size: self.size,
+        }
+    }
+
+    /// The shuffle that puts the deck back.  Panics if this one isn't
+    /// reversible, which only happens dealing with an increment that
+    /// shares a factor with the deck size, as `new` refuses to.
+    pub fn inverse(&self) -> Shuffle {
+        let gcd = self.a.extended_gcd(&self.size);
+        assert_eq!(gcd.gcd, 1, "Shuffle can't be undone");
+        let a = gcd.x.rem_euclid(self.size);
+        Shuffle {
+            a,
+            b: (self.size - self.b) * a % self.size,
+            size: self.size,
+        }
+    }
+
+    /// This shuffle done `times` times over.
+    pub fn repeat(&self, times: u64) -> Shuffle {
+        // b * (a^n - 1) / (a - 1), worked mod size * (a - 1) so the division
+        // is exact even when a - 1 has no inverse mod size
+        let b = if self.a == 1 {
+            self.b * (times as i128 % self.size) % self.size
+        } else {
+            let d = BigInt::from(self.a - 1);
+            let modulus = &d * BigInt::from(self.size);
+            let an =
+                BigInt::from(self.a).modpow(&BigInt::from(times), &modulus);
+            let series: BigInt = ((an + &modulus - 1u8) % &modulus) / d;
+            let series = series % BigInt::from(self.size);
+            self.b * series.to_i128().unwrap() % self.size
+        };
+        Shuffle {
+            a: modpow(self.a, times, self.size),
+            b,
+            size: self.size,
+        }
+    }
+
+    /// Where `card` ends up.
+    pub fn position(&self, card: i64) -> i64 {
+        ((self.a * card as i128 + self.b) % self.size) as i64
+    }
+
+    /// Which card ends up at `position`.
+    pub fn card_at(&self, position: i64) -> i64 {
+        self.inverse().position(position)
+    }
+}
+
+pub fn part1(input: &[String]) -> i64 {
+    Shuffle::new(&parse_input(input), 10007)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .position(2019)
+}
+
+pub fn part2(input: &[String]) -> i64 {
+    Shuffle::new(&parse_input(input), 119_315_717_514_047)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .repeat(101_741_582_076_661)
+        .card_at(2020)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLES: [(&str, [usize; 10]); 4] = [
+        (
+            "deal with increment 7\ndeal into new stack\ndeal into new stack",
+            [0, 3, 6, 9, 2, 5, 8, 1, 4, 7],
+        ),
+        (
+            "cut 6\ndeal with increment 7\ndeal into new stack",
+            [3, 0, 7, 4, 1, 8, 5, 2, 9, 6],
+        ),
+        (
+            "deal with increment 7\ndeal with increment 9\ncut -2",
+            [6, 3, 0, 7, 4, 1, 8, 5, 2, 9],
+        ),
+        (
+            "deal into new stack\ncut -2\ndeal with increment 7\ncut 8\n\
+             cut -4\ndeal with increment 7\ncut 3\ndeal with increment 9\n\
+             deal with increment 3\ncut -1",
+            [9, 2, 5, 8, 1, 4, 7, 0, 3, 6],
+        ),
+    ];
+
+    fn techniques(text: &str) -> Vec<Technique> {
+        let lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+        parse_input(&lines)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            techniques("deal into new stack\ncut -2\ndeal with increment 7"),
+            vec![
+                Technique::NewStack,
+                Technique::Cut(-2),
+                Technique::Increment(7)
+            ]
+        );
+        assert_eq!(Technique::parse("cut the deck"), None);
+        assert_eq!(Technique::parse("deal with increment 0"), None);
+        assert_eq!(Technique::parse("deal with increment -3"), None);
+
+        // Dealing with 4 would put cards 0 and 5 of 10 in the same space
+        let shuffle = Shuffle::new(&techniques("deal with increment 4"), 10);
+        assert_eq!(
+            shuffle,
+            Err("Can't deal with increment 4 on a deck of 10".to_string())
+        );
+        assert!(Shuffle::new(&techniques("deal with increment 4"), 11).is_ok());
+    }
+
+    #[test]
+    fn test_part1() {
+        for (text, deck) in EXAMPLES.iter() {
+            let techniques = techniques(text);
+            assert_eq!(simulate(&techniques, 10), deck.to_vec());
+
+            let shuffle = Shuffle::new(&techniques, 10).unwrap();
+            for (position, card) in deck.iter().enumerate() {
+                assert_eq!(shuffle.position(*card as i64), position as i64);
+                assert_eq!(shuffle.card_at(position as i64), *card as i64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_part2() {
+        let techniques = techniques(EXAMPLES[3].0);
+        for &size in [10, 10007].iter() {
+            let shuffle = Shuffle::new(&techniques, size as i64).unwrap();
+            let mut deck: Vec<usize> = (0..size).collect();
+            for times in 0..20 {
+                let repeated = shuffle.repeat(times);
+                for (position, card) in deck.iter().enumerate() {
+                    assert_eq!(repeated.card_at(position as i64), *card as i64);
+                }
+                let once = simulate(&techniques, size);
+                deck = once.iter().map(|&i| deck[i]).collect();
+            }
+        }
+
+        // Undoing a huge number of shuffles gets the deck back
+        let size = 119_315_717_514_047;
+        let shuffle = Shuffle::new(&techniques, size)
+            .unwrap()
+            .repeat(101_741_582_076_661);
+        assert_eq!(shuffle.then(&shuffle.inverse()), Shuffle::identity(size));
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::computer::{load_program, ProgramState, ProgramStatus};
+
+pub const NAT: usize = 255;
+
+pub fn load_input(name: &str) -> Vec<i64> {
+    load_program(name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Packet {
+    pub from: usize,
+    pub to: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}: ({}, {})", self.from, self.to, self.x, self.y)
+    }
+}
+
+/// A Category Six network of NICs all running the same program, plus the
+/// NAT at address 255.  Every packet sent, including the NAT's wake-up
+/// packets and any addressed to nobody, is kept in `log`.
+pub struct Network {
+    pub nics: Vec<ProgramState>,
+    queues: Vec<VecDeque<(i64, i64)>>,
+    pub nat: Option<(i64, i64)>,
+    pub log: Vec<Packet>,
+}
+
+impl Network {
+    /// Boots `size` NICs, handing each its address.
+    pub fn new(program: &[i64], size: usize) -> Network {
+        let nics = (0..size)
+            .map(|address| {
+                let mut nic = ProgramState::new(program);
+                nic.push_input(address as i64);
+                nic
+            })
+            .collect();
+        Network {
+            nics,
+            queues: vec![VecDeque::new(); size],
+            nat: None,
+            log: vec![],
+        }
+    }
+
+    /// Delivers a packet and logs it.  Packets for an address with no NIC
+    /// are only logged.
+    fn send(&mut self, packet: Packet) {
+        match usize::try_from(packet.to) {
+            Ok(NAT) => self.nat = Some((packet.x, packet.y)),
+            Ok(to) if to < self.queues.len() => {
+                self.queues[to].push_back((packet.x, packet.y));
+            }
+            _ => (),
+        }
+        self.log.push(packet);
+    }
+
+    /// Gives every NIC its queued packets, or -1 if it has none, and runs it
+    /// until it wants more input.  Returns the packets sent along the way.
+    /// Halted NICs are skipped, and packets sent to them are dropped.
+    pub fn tick(&mut self) -> Vec<Packet> {
+        let mut sent = vec![];
+        for from in 0..self.nics.len() {
+            let nic = &mut self.nics[from];
+            if nic.status == ProgramStatus::Halted {
+                self.queues[from].clear();
+                continue;
+            }
+            if self.queues[from].is_empty() {
+                nic.push_input(-1);
+            }
+            for (x, y) in self.queues[from].drain(..) {
+                nic.push_inputs([x, y].iter().cloned());
+            }
+            nic.run_until_blocked();
+
+            // Leave a partly written packet for the next tick
+            let complete = nic.output.len() - nic.output.len() % 3;
+            let output: Vec<i64> = nic.output.drain(..complete).collect();
+            for chunk in output.chunks(3) {
+                let packet = Packet {
+                    from,
+                    to: chunk[0],
+                    x: chunk[1],
+                    y: chunk[2],
+                };
+                self.send(packet);
+                sent.push(packet);
+            }
+        }
+        sent
+    }
+
+    /// Whether the last tick sent nothing and left nothing queued or half
+    /// written.
+    fn settled(&self, sent: &[Packet]) -> bool {
+        sent.is_empty()
+            && self.queues.iter().all(|queue| queue.is_empty())
+            && self.nics.iter().all(|nic| nic.output.is_empty())
+    }
+
+    fn halted(&self) -> bool {
+        self.nics
+            .iter()
+            .all(|nic| nic.status == ProgramStatus::Halted)
+    }
+
+    /// Has the NAT resend the last packet it received to address 0.
+    pub fn wake(&mut self) -> Option<Packet> {
+        let (x, y) = self.nat?;
+        let packet = Packet {
+            from: NAT,
+            to: 0,
+            x,
+            y,
+        };
+        self.send(packet);
+        Some(packet)
+    }
+
+    /// Y value of the first packet sent to the NAT, or `None` if every NIC
+    /// halts first.
+    pub fn first_nat_packet(&mut self) -> Option<i64> {
+        while !self.halted() {
+            for packet in self.tick() {
+                if packet.to == NAT as i64 {
+                    return Some(packet.y);
+                }
+            }
+        }
+        None
+    }
+
+    /// Y value of the first packet the NAT delivers to address 0 twice in
+    /// a row, or `None` if every NIC halts first or the network goes idle
+    /// before the NAT has anything to send.
+    pub fn first_repeated_wake(&mut self) -> Option<i64> {
+        let mut last_y = None;
+        while !self.halted() {
+            let sent = self.tick();
+            if !self.settled(&sent) {
+                continue;
+            }
+            let packet = self.wake()?;
+            if last_y == Some(packet.y) {
+                return Some(packet.y);
+            }
+            last_y = Some(packet.y);
+        }
+        None
+    }
+}
+
+pub fn part1(input: &[i64]) -> i64 {
+    Network::new(input, 50)
+        .first_nat_packet()
+        .expect("Every NIC halted before sending to the NAT")
+}
+
+pub fn part2(input: &[i64]) -> i64 {
+    Network::new(input, 50)
+        .first_repeated_wake()
+        .expect("The NAT never sent the same packet twice")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asm::assemble;
+
+    // NIC 0 starts a packet going round the ring, each NIC bumping y until
+    // it reaches 110, and the last NIC sends it to the NAT
+    const RING: &str = "
+            in -> [addr]
+            eq [addr], #0 -> [t]
+            jf [t], #loop
+            out #1
+            out #7
+            out #100
+        loop:
+            in -> [x]
+            eq [x], #-1 -> [t]
+            jt [t], #loop
+            in -> [y]
+            add [addr], #1 -> [to]
+            eq [to], #3 -> [t]
+            jf [t], #send
+            add #255, #0 -> [to]
+        send:
+            lt [y], #110 -> [t]
+            add [y], [t] -> [y]
+            out [to]
+            out [x]
+            out [y]
+            jt #1, #loop
+        addr: .data 0
+        x: .data 0
+        y: .data 0
+        t: .data 0
+        to: .data 0
+    ";
+
+    #[test]
+    fn test_part1() {
+        let program = assemble(RING).unwrap();
+        let mut network = Network::new(&program, 3);
+        assert_eq!(network.first_nat_packet(), Some(102));
+        let log: Vec<String> =
+            network.log.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            log,
+            vec!["0 -> 1: (7, 100)", "1 -> 2: (7, 101)", "2 -> 255: (7, 102)"]
+        );
+    }
+
+    #[test]
+    fn test_bad_address() {
+        let program = assemble(
+            "
+            out #-5
+            out #1
+            out #2
+            out #9
+            out #3
+            out #4
+            hlt
+        ",
+        )
+        .unwrap();
+        let mut network = Network::new(&program, 2);
+        let sent = network.tick();
+        assert_eq!(sent.len(), 4);
+        assert_eq!(network.log, sent);
+        assert_eq!(network.log[0].to_string(), "0 -> -5: (1, 2)");
+        assert!(network.settled(&[]));
+        assert_eq!(network.nat, None);
+
+        // Both NICs have halted, so there's nothing more to wait for
+        assert!(network.tick().is_empty());
+        assert_eq!(network.first_nat_packet(), None);
+        assert_eq!(network.first_repeated_wake(), None);
+    }
+
+    #[test]
+    fn test_part2() {
+        let program = assemble(RING).unwrap();
+        let mut network = Network::new(&program, 3);
+        assert_eq!(network.first_repeated_wake(), Some(110));
+        let wakes: Vec<i64> = network
+            .log
+            .iter()
+            .filter(|p| p.from == NAT)
+            .map(|p| p.y)
+            .collect();
+        assert_eq!(wakes, vec![102, 105, 108, 110, 110]);
+    }
+}
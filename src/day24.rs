@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+const SIZE: i32 = 5;
+const CENTER: i32 = 12;
+
+pub fn load_input(name: &str) -> Vec<String> {
+    let f = BufReader::new(File::open(name).unwrap());
+    f.lines().map(|x| x.unwrap()).collect()
+}
+
+/// Packs the grid into 25 bits, one per tile in reading order.
+pub fn parse_grid(input: &[String]) -> u32 {
+    input
+        .iter()
+        .flat_map(|line| line.trim().chars())
+        .enumerate()
+        .filter(|(_, c)| *c == '#')
+        .fold(0, |grid, (i, _)| grid | 1 << i)
+}
+
+fn has_bug(grid: u32, x: i32, y: i32) -> bool {
+    grid & 1 << (y * SIZE + x) != 0
+}
+
+/// A bug survives with exactly one neighbour, and an empty tile becomes
+/// infested with one or two.
+fn lives(bug: bool, neighbours: u32) -> bool {
+    neighbours == 1 || (!bug && neighbours == 2)
+}
+
+/// One minute on a single, flat grid.
+pub fn step(grid: u32) -> u32 {
+    let mut next = 0;
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let neighbours = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                .iter()
+                .map(|(dx, dy)| (x + dx, y + dy))
+                .filter(|&(nx, ny)| {
+                    (0..SIZE).contains(&nx)
+                        && (0..SIZE).contains(&ny)
+                        && has_bug(grid, nx, ny)
+                })
+                .count() as u32;
+            if lives(has_bug(grid, x, y), neighbours) {
+                next |= 1 << (y * SIZE + x);
+            }
+        }
+    }
+    next
+}
+
+/// Each tile is worth twice the one before it, which is just the bitmask.
+pub fn biodiversity(grid: u32) -> u32 {
+    grid
+}
+
+/// The first layout to appear twice.
+pub fn first_repeat(mut grid: u32) -> u32 {
+    let mut seen = HashSet::new();
+    while seen.insert(grid) {
+        grid = step(grid);
+    }
+    grid
+}
+
+/// Bugs on the tile at `(x, y)` of `depth`, plus any tiles of the grids
+/// inside or around it that border it from direction `(dx, dy)`.
+fn count_towards(
+    levels: &BTreeMap<i32, u32>,
+    depth: i32,
+    (x, y): (i32, i32),
+    (dx, dy): (i32, i32),
+) -> u32 {
+    let grid = |depth| levels.get(&depth).cloned().unwrap_or(0);
+    let (nx, ny) = (x + dx, y + dy);
+
+    if !(0..SIZE).contains(&nx) || !(0..SIZE).contains(&ny) {
+        // Off the edge, onto the tile next to the center of the outer grid
+        has_bug(grid(depth - 1), 2 + dx, 2 + dy) as u32
+    } else if ny * SIZE + nx == CENTER {
+        // Into the center, touching a whole edge of the inner grid
+        let inner = grid(depth + 1);
+        (0..SIZE)
+            .map(|i| match (dx, dy) {
+                (1, 0) => (0, i),
+                (-1, 0) => (SIZE - 1, i),
+                (0, 1) => (i, 0),
+                _ => (i, SIZE - 1),
+            })
+            .filter(|&(ix, iy)| has_bug(inner, ix, iy))
+            .count() as u32
+    } else {
+        has_bug(grid(depth), nx, ny) as u32
+    }
+}
+
+/// One minute on the recursive grids, keyed by depth with inner grids
+/// deeper.  Only grids with bugs are kept, and one more level each way is
+/// considered every minute so the set grows as the bugs spread.
+pub fn step_recursive(levels: &BTreeMap<i32, u32>) -> BTreeMap<i32, u32> {
+    let (min, max) = match (levels.keys().next(), levels.keys().last()) {
+        (Some(min), Some(max)) => (*min, *max),
+        _ => return BTreeMap::new(),
+    };
+
+    let mut next = BTreeMap::new();
+    for depth in min - 1..=max + 1 {
+        let grid = levels.get(&depth).cloned().unwrap_or(0);
+        let mut new_grid = 0;
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                if y * SIZE + x == CENTER {
+                    continue;
+                }
+                let neighbours: u32 = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                    .iter()
+                    .map(|&d| count_towards(levels, depth, (x, y), d))
+                    .sum();
+                if lives(has_bug(grid, x, y), neighbours) {
+                    new_grid |= 1 << (y * SIZE + x);
+                }
+            }
+        }
+        if new_grid != 0 {
+            next.insert(depth, new_grid);
+        }
+    }
+    next
+}
+
+pub fn bugs_after(grid: u32, minutes: usize) -> u32 {
+    let mut levels = BTreeMap::new();
+    levels.insert(0, grid & !(1 << CENTER));
+    for _ in 0..minutes {
+        levels = step_recursive(&levels);
+    }
+    levels.values().map(|grid| grid.count_ones()).sum()
+}
+
+pub fn part1(input: &[String]) -> u32 {
+    biodiversity(first_repeat(parse_grid(input)))
+}
+
+pub fn part2(input: &[String]) -> u32 {
+    bugs_after(parse_grid(input), 200)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grid(text: &str) -> u32 {
+        let lines: Vec<String> = text
+            .split_whitespace()
+            .map(|line| line.to_string())
+            .collect();
+        parse_grid(&lines)
+    }
+
+    const EXAMPLE: &str = "
+        ....#
+        #..#.
+        #..##
+        ..#..
+        #....";
+
+    #[test]
+    fn test_step() {
+        let after = grid(
+            "
+            #..#.
+            ####.
+            ###.#
+            ##.##
+            .##..",
+        );
+        assert_eq!(step(grid(EXAMPLE)), after);
+    }
+
+    #[test]
+    fn test_part1() {
+        let repeat = first_repeat(grid(EXAMPLE));
+        assert_eq!(
+            repeat,
+            grid(
+                "
+                .....
+                .....
+                .....
+                #....
+                .#..."
+            )
+        );
+        assert_eq!(biodiversity(repeat), 2129920);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(bugs_after(grid(EXAMPLE), 10), 99);
+    }
+}
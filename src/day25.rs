@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::io::prelude::*;
+
+use crate::ascii::AsciiMachine;
+use crate::computer::{load_program, StepResult};
+
+/// Items that end the game or hang the droid when picked up.
+pub const BLOCKLIST: [&str; 5] = [
+    "escape pod",
+    "giant electromagnet",
+    "infinite loop",
+    "molten lava",
+    "photons",
+];
+
+const CHECKPOINT: &str = "Security Checkpoint";
+
+pub fn load_input(name: &str) -> Vec<i64> {
+    load_program(name)
+}
+
+/// Something that plays the game: takes commands and prints text back.
+pub trait Console {
+    /// Everything printed since the last command.
+    fn read(&mut self) -> String;
+    fn write(&mut self, command: &str);
+}
+
+impl Console for AsciiMachine {
+    fn read(&mut self) -> String {
+        self.run().text
+    }
+
+    fn write(&mut self, command: &str) {
+        self.send_line(command);
+    }
+}
+
+/// Plays the game with stdin and stdout, until it ends or input runs out.
+pub fn play<R: BufRead, W: Write>(
+    program: &[i64],
+    input: R,
+    mut output: W,
+) -> io::Result<()> {
+    let mut droid = AsciiMachine::new(program);
+    let mut lines = input.lines();
+    loop {
+        let screen = droid.run();
+        write!(output, "{}", screen.text)?;
+        output.flush()?;
+        if screen.result == StepResult::Halted {
+            return Ok(());
+        }
+        match lines.next() {
+            Some(line) => droid.send_line(line?.trim()),
+            None => return Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Room {
+    pub name: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+/// Parses the last room described in `text`.  Being thrown back from the
+/// pressure-sensitive floor describes two rooms, and the droid ends up in
+/// the second.
+pub fn parse_room(text: &str) -> Option<Room> {
+    let start = text.rfind("== ")?;
+    let mut lines = text[start..].lines();
+    let name = lines.next()?.trim_matches(|c| c == '=' || c == ' ');
+    let mut room = Room {
+        name: name.to_string(),
+        ..Room::default()
+    };
+
+    let mut list = None;
+    for line in lines {
+        match line {
+            "Doors here lead:" => list = Some(&mut room.doors),
+            "Items here:" => list = Some(&mut room.items),
+            _ => match (line.strip_prefix("- "), &mut list) {
+                (Some(entry), Some(list)) => list.push(entry.to_string()),
+                _ => list = None,
+            },
+        }
+    }
+    Some(room)
+}
+
+fn opposite(direction: &str) -> &'static str {
+    match direction {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        _ => panic!("Unknown direction {}", direction),
+    }
+}
+
+/// Maps the ship, picks up everything safe, then finds the combination of
+/// items that gets past the pressure-sensitive floor.
+pub struct Explorer<C: Console> {
+    console: C,
+    pub rooms: HashMap<String, Room>,
+    /// Room reached by each known door, keyed by room then direction.
+    pub doors: HashMap<String, HashMap<String, String>>,
+    pub inventory: Vec<String>,
+    /// The checkpoint door that leads onto the pressure-sensitive floor.
+    pub floor: Option<String>,
+    pub log: Vec<String>,
+}
+
+impl<C: Console> Explorer<C> {
+    pub fn new(console: C) -> Explorer<C> {
+        Explorer {
+            console,
+            rooms: HashMap::new(),
+            doors: HashMap::new(),
+            inventory: vec![],
+            floor: None,
+            log: vec![],
+        }
+    }
+
+    fn send(&mut self, command: &str) -> String {
+        self.log.push(command.to_string());
+        self.console.write(command);
+        self.console.read()
+    }
+
+    fn link(&mut self, from: &str, direction: &str, to: &str) {
+        self.doors
+            .entry(from.to_string())
+            .or_default()
+            .insert(direction.to_string(), to.to_string());
+    }
+
+    // Depth first, coming back to `room` after each door
+    fn explore_from(&mut self, room: Room) {
+        for item in &room.items {
+            if !BLOCKLIST.contains(&item.as_str()) {
+                self.send(&format!("take {}", item));
+                self.inventory.push(item.clone());
+            }
+        }
+        self.rooms.insert(room.name.clone(), room.clone());
+
+        for door in &room.doors {
+            let known = self.doors.get(&room.name);
+            if known.is_some_and(|doors| doors.contains_key(door)) {
+                continue;
+            }
+            let text = self.send(door);
+            let next = parse_room(&text).expect("Moved nowhere");
+            if next.name == room.name {
+                // Thrown back off the pressure-sensitive floor
+                self.floor = Some(door.clone());
+                continue;
+            }
+
+            self.link(&room.name, door, &next.name);
+            self.link(&next.name, opposite(door), &room.name);
+            if !self.rooms.contains_key(&next.name) {
+                self.explore_from(next);
+            }
+            self.send(opposite(door));
+        }
+    }
+
+    /// Visits every room reachable from the start, ending back there.
+    pub fn explore(&mut self) -> String {
+        let text = self.console.read();
+        let start = parse_room(&text).expect("No starting room");
+        let name = start.name.clone();
+        self.explore_from(start);
+        name
+    }
+
+    /// Directions from one known room to another.
+    pub fn path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<&str, (&str, &str)> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(from);
+        queue.push_back(from);
+
+        while let Some(room) = queue.pop_front() {
+            if room == to {
+                let mut path = vec![];
+                let mut room = to;
+                while room != from {
+                    let (before, direction) = previous[room];
+                    path.push(direction.to_string());
+                    room = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (direction, next) in self.doors.get(room)? {
+                if seen.insert(next.as_str()) {
+                    previous.insert(next, (room, direction));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Tries every subset of the inventory on the floor, toggling one item
+    /// at a time in Gray code order.  Returns the final text once the
+    /// droid gets through.
+    pub fn crack_floor(&mut self) -> Option<String> {
+        let floor = self.floor.clone()?;
+        let items = self.inventory.clone();
+        for item in &items {
+            self.send(&format!("drop {}", item));
+        }
+
+        let mut held = 0usize;
+        for i in 0..1usize << items.len() {
+            let gray = i ^ (i >> 1);
+            let changed = gray ^ held;
+            if changed != 0 {
+                let bit = changed.trailing_zeros() as usize;
+                let verb = if gray & changed != 0 { "take" } else { "drop" };
+                self.send(&format!("{} {}", verb, items[bit]));
+            }
+            held = gray;
+
+            let text = self.send(&floor);
+            if !text.contains("Alert!") {
+                return Some(text);
+            }
+        }
+        None
+    }
+
+    /// Explores, walks to the checkpoint and gets past the floor, returning
+    /// the airlock password.
+    pub fn solve(&mut self) -> Option<i64> {
+        let start = self.explore();
+        for direction in self.path(&start, CHECKPOINT)? {
+            self.send(&direction);
+        }
+        let text = self.crack_floor()?;
+        let words: Vec<&str> = text.split_whitespace().collect();
+        words
+            .windows(2)
+            .find(|pair| pair[0] == "typing")
+            .and_then(|pair| pair[1].parse().ok())
+    }
+}
+
+pub fn part1(input: &[i64]) -> i64 {
+    Explorer::new(AsciiMachine::new(input))
+        .solve()
+        .expect("Couldn't get past the security checkpoint")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Hull Breach - north - Kitchen - east - Security Checkpoint - north -
+    // floor, with a dead end west of the start
+    struct Ship {
+        room: &'static str,
+        items: HashMap<&'static str, Vec<&'static str>>,
+        inventory: Vec<&'static str>,
+        pending: String,
+    }
+
+    fn doors(room: &str) -> Vec<(&'static str, &'static str)> {
+        match room {
+            "Hull Breach" => vec![("north", "Kitchen"), ("west", "Closet")],
+            "Closet" => vec![("east", "Hull Breach")],
+            "Kitchen" => vec![("south", "Hull Breach"), ("east", CHECKPOINT)],
+            CHECKPOINT => vec![("west", "Kitchen"), ("north", "Floor")],
+            _ => vec![],
+        }
+    }
+
+    impl Ship {
+        fn new() -> Ship {
+            let mut items = HashMap::new();
+            items.insert("Hull Breach", vec!["mug"]);
+            items.insert("Closet", vec!["infinite loop", "spool of cat6"]);
+            items.insert("Kitchen", vec!["wreath", "hypercube"]);
+            Ship {
+                room: "Hull Breach",
+                items,
+                inventory: vec![],
+                pending: String::new(),
+            }
+        }
+
+        fn describe(&self, room: &str) -> String {
+            let mut text =
+                format!("\n\n\n== {} ==\nA room.\n\nDoors here lead:\n", room);
+            for (direction, _) in doors(room) {
+                text += &format!("- {}\n", direction);
+            }
+            if let Some(items) = self.items.get(room).filter(|i| !i.is_empty())
+            {
+                text += "\nItems here:\n";
+                for item in items {
+                    text += &format!("- {}\n", item);
+                }
+            }
+            text + "\nCommand?\n"
+        }
+    }
+
+    impl Console for Ship {
+        fn read(&mut self) -> String {
+            if self.pending.is_empty() {
+                return self.describe(self.room);
+            }
+            std::mem::take(&mut self.pending)
+        }
+
+        fn write(&mut self, command: &str) {
+            if let Some(item) = command.strip_prefix("take ") {
+                let items = self.items.get_mut(self.room).unwrap();
+                let i = items.iter().position(|x| *x == item).unwrap();
+                assert_ne!(item, "infinite loop");
+                self.inventory.push(items.remove(i));
+                self.pending =
+                    format!("\nYou take the {}.\n\nCommand?\n", item);
+            } else if let Some(item) = command.strip_prefix("drop ") {
+                let i = self.inventory.iter().position(|x| *x == item).unwrap();
+                let item = self.inventory.remove(i);
+                self.items.entry(self.room).or_default().push(item);
+                self.pending =
+                    format!("\nYou drop the {}.\n\nCommand?\n", item);
+            } else {
+                let (_, next) = doors(self.room)
+                    .into_iter()
+                    .find(|(direction, _)| *direction == command)
+                    .unwrap();
+                if next == "Floor" {
+                    let mut held = self.inventory.clone();
+                    held.sort();
+                    if held == vec!["mug", "wreath"] {
+                        self.pending = "\n== Pressure-Sensitive Floor ==\n\
+                            You should be able to get in by typing 8675309 \
+                            on the keypad at the main airlock.\n"
+                            .to_string();
+                    } else {
+                        self.pending = "\n== Pressure-Sensitive Floor ==\n\
+                            Alert! Droids on this ship are heavier than the \
+                            detected value!\n"
+                            .to_string()
+                            + &self.describe(CHECKPOINT);
+                    }
+                } else {
+                    self.room = next;
+                    self.pending = self.describe(next);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_room() {
+        let ship = Ship::new();
+        assert_eq!(
+            parse_room(&ship.describe("Closet")),
+            Some(Room {
+                name: "Closet".to_string(),
+                doors: vec!["east".to_string()],
+                items: vec![
+                    "infinite loop".to_string(),
+                    "spool of cat6".to_string()
+                ],
+            })
+        );
+        let thrown_back = "== Pressure-Sensitive Floor ==\nAlert!\n"
+            .to_string()
+            + &ship.describe(CHECKPOINT);
+        assert_eq!(parse_room(&thrown_back).unwrap().name, CHECKPOINT);
+        assert_eq!(parse_room("Command?"), None);
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut explorer = Explorer::new(Ship::new());
+        assert_eq!(explorer.explore(), "Hull Breach");
+        assert_eq!(explorer.rooms.len(), 4);
+        assert_eq!(explorer.console.room, "Hull Breach");
+        assert_eq!(explorer.floor, Some("north".to_string()));
+        assert!(!explorer.inventory.contains(&"infinite loop".to_string()));
+        assert_eq!(explorer.inventory.len(), 4);
+        assert_eq!(
+            explorer.path("Closet", CHECKPOINT),
+            Some(vec![
+                "east".to_string(),
+                "north".to_string(),
+                "east".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut explorer = Explorer::new(Ship::new());
+        assert_eq!(explorer.solve(), Some(8675309));
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+
+use crate::computer::{
+    self, IntcodeError, ProgramState, ProgramStatus, StepResult,
+};
+use crate::disasm;
+
+const HELP: &str = "\
+step [n]         (s)  execute n instructions, default 1
+continue         (c)  run until a breakpoint, watchpoint, halt or missing input
+break <addr>     (b)  stop before the instruction at addr runs
+watch <addr>     (w)  stop after the value at addr changes
+delete <addr>    (d)  remove a breakpoint or watchpoint
+info                  list breakpoints and watchpoints
+regs             (r)  show ic, relative base, status, cycles and input
+mem <addr> [n]   (x)  dump n memory cells, default 16
+list [addr] [n]  (l)  disassemble n instructions, default 8 from ic
+input <values>   (i)  queue comma or space separated input values
+feed <file>           queue every value in a file as input
+output           (o)  show and clear collected output
+save <file>           write a snapshot of the machine
+load <file>           restore a snapshot
+quit             (q)";
+
+/// Something worth telling the user about after executing an instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Stepped,
+    Output(i64),
+    NeedInput,
+    Halted,
+    BudgetExceeded,
+    Breakpoint(usize),
+    Watchpoint { address: usize, old: i64, new: i64 },
+    Error(IntcodeError),
+}
+
+/// Interactive front end over a single `ProgramState`, driving it one
+/// instruction at a time with `computer::step`.
+pub struct Debugger {
+    pub state: ProgramState,
+    pub breakpoints: BTreeSet<usize>,
+    pub watchpoints: BTreeSet<usize>,
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("Not a number: {:?}", text))
+}
+
+/// Parses comma or whitespace separated input values.
+pub fn parse_values(text: &str) -> Result<Vec<i64>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .map(parse_number)
+        .collect()
+}
+
+impl Debugger {
+    pub fn new(state: ProgramState) -> Debugger {
+        Debugger {
+            state,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Executes one instruction, keeping any output in the state's output
+    /// buffer.
+    pub fn step(&mut self) -> Event {
+        let watched: Vec<(usize, i64)> = self
+            .watchpoints
+            .iter()
+            .map(|address| (*address, self.state.memory[*address]))
+            .collect();
+
+        self.state.status = ProgramStatus::Running;
+        match computer::step(&mut self.state) {
+            Err(e) => return Event::Error(e),
+            Ok(Some(StepResult::Output(x))) => {
+                self.state.output.push(x);
+                return Event::Output(x);
+            }
+            Ok(Some(StepResult::NeedInput)) => return Event::NeedInput,
+            Ok(Some(StepResult::Halted)) => return Event::Halted,
+            Ok(Some(StepResult::BudgetExceeded)) => {
+                return Event::BudgetExceeded
+            }
+            Ok(None) => (),
+        }
+
+        for (address, old) in watched {
+            let new = self.state.memory[address];
+            if new != old {
+                return Event::Watchpoint { address, old, new };
+            }
+        }
+        Event::Stepped
+    }
+
+    /// Steps until something other than a plain instruction or an output
+    /// happens, or `ic` lands on a breakpoint.  Outputs seen along the way
+    /// are appended to `outputs`.
+    pub fn cont(&mut self, outputs: &mut Vec<i64>) -> Event {
+        loop {
+            match self.step() {
+                Event::Stepped => (),
+                Event::Output(x) => outputs.push(x),
+                event => return event,
+            }
+            if self.breakpoints.contains(&self.state.ic) {
+                return Event::Breakpoint(self.state.ic);
+            }
+        }
+    }
+
+    /// Disassembles the instruction at `address`, returning its text and
+    /// length.  Anything that doesn't decode is shown as a single data
+    /// value.
+    fn describe(&self, address: usize) -> (String, usize) {
+        let raw: Vec<i64> = (address..address + 4)
+            .map(|a| self.state.memory[a])
+            .collect();
+        match disasm::decode_at(&raw, 0) {
+            Some((inst, params)) => (
+                disasm::format_instruction(&inst, params, &BTreeSet::new()),
+                inst.len(),
+            ),
+            None => (format!(".data {}", raw[0]), 1),
+        }
+    }
+
+    // `=>` marks the current instruction and `*` a breakpoint
+    fn line(&self, address: usize, text: &str) -> String {
+        let marker = if address == self.state.ic {
+            "=>"
+        } else if self.breakpoints.contains(&address) {
+            " *"
+        } else {
+            "  "
+        };
+        format!("{} {:>6}  {}", marker, address, text)
+    }
+
+    fn current(&self) -> String {
+        self.line(self.state.ic, &self.describe(self.state.ic).0)
+    }
+
+    fn report(&self, event: &Event) -> Option<String> {
+        match event {
+            Event::Stepped => None,
+            Event::Output(x) => Some(format!("output {}", x)),
+            Event::NeedInput => Some("waiting for input".to_string()),
+            Event::Halted => Some("halted".to_string()),
+            Event::BudgetExceeded => Some(format!(
+                "instruction budget exhausted after {} cycles",
+                self.state.cycles
+            )),
+            Event::Breakpoint(address) => {
+                Some(format!("breakpoint at {}", address))
+            }
+            Event::Watchpoint { address, old, new } => {
+                Some(format!("watchpoint [{}]: {} -> {}", address, old, new))
+            }
+            Event::Error(e) => Some(format!("error: {}", e)),
+        }
+    }
+
+    fn queue_input(&mut self, text: &str) -> Result<Vec<String>, String> {
+        let values = parse_values(text)?;
+        let n = values.len();
+        self.state.push_inputs(values);
+        Ok(vec![format!(
+            "queued {} value(s), {} waiting",
+            n,
+            self.state.input.len()
+        )])
+    }
+
+    /// Queues every value in a comma or whitespace separated file as input.
+    pub fn feed(&mut self, path: &str) -> Result<Vec<String>, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        self.queue_input(&text)
+    }
+
+    fn commands(&mut self, line: &str) -> Result<Vec<String>, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let args: Vec<&str> = words.collect();
+        let arg = |i: usize| -> Result<&str, String> {
+            args.get(i)
+                .copied()
+                .ok_or_else(|| format!("{} needs more arguments", command))
+        };
+
+        let mut lines = vec![];
+        match command {
+            "s" | "step" => {
+                let n = match args.first() {
+                    Some(n) => parse_number(n)?,
+                    None => 1,
+                };
+                for _ in 0..n {
+                    lines.push(self.current());
+                    let event = self.step();
+                    let stop =
+                        !matches!(event, Event::Stepped | Event::Output(_));
+                    lines.extend(self.report(&event));
+                    if stop {
+                        return Ok(lines);
+                    }
+                }
+                lines.push(self.current());
+            }
+            "c" | "continue" => {
+                let mut outputs = vec![];
+                let event = self.cont(&mut outputs);
+                lines.extend(outputs.iter().map(|x| format!("output {}", x)));
+                lines.extend(self.report(&event));
+                lines.push(self.current());
+            }
+            "b" | "break" => {
+                let address = parse_number(arg(0)?)?;
+                self.breakpoints.insert(address);
+                lines.push(format!("breakpoint at {}", address));
+            }
+            "w" | "watch" => {
+                let address = parse_number(arg(0)?)?;
+                self.watchpoints.insert(address);
+                lines.push(format!("watching [{}]", address));
+            }
+            "d" | "delete" => {
+                let address = parse_number(arg(0)?)?;
+                let removed = self.breakpoints.remove(&address)
+                    | self.watchpoints.remove(&address);
+                if !removed {
+                    return Err(format!("Nothing set at {}", address));
+                }
+            }
+            "info" => {
+                for address in &self.breakpoints {
+                    lines.push(format!("break {}", address));
+                }
+                for address in &self.watchpoints {
+                    lines.push(format!(
+                        "watch [{}] = {}",
+                        address, self.state.memory[*address]
+                    ));
+                }
+            }
+            "r" | "regs" => {
+                let input: Vec<String> =
+                    self.state.input.iter().map(|x| x.to_string()).collect();
+                lines.push(format!(
+                    "ic {}  relative_base {}  status {:?}  cycles {}  input [{}]",
+                    self.state.ic,
+                    self.state.relative_base,
+                    self.state.status,
+                    self.state.cycles,
+                    input.join(", ")
+                ));
+                lines.push(self.current());
+            }
+            "x" | "mem" => {
+                let start: usize = parse_number(arg(0)?)?;
+                let n: usize = match args.get(1) {
+                    Some(n) => parse_number(n)?,
+                    None => 16,
+                };
+                for row in (start..start + n).step_by(8) {
+                    let values: Vec<String> = (row..(row + 8).min(start + n))
+                        .map(|a| format!("{:>8}", self.state.memory[a]))
+                        .collect();
+                    lines.push(format!("{:>8}:{}", row, values.join("")));
+                }
+            }
+            "l" | "list" => {
+                let mut address = match args.first() {
+                    Some(address) => parse_number(address)?,
+                    None => self.state.ic,
+                };
+                let n = match args.get(1) {
+                    Some(n) => parse_number(n)?,
+                    None => 8,
+                };
+                for _ in 0..n {
+                    let (text, len) = self.describe(address);
+                    lines.push(self.line(address, &text));
+                    address += len;
+                }
+            }
+            "i" | "input" => {
+                return self.queue_input(&args.join(" "));
+            }
+            "feed" => return self.feed(arg(0)?),
+            "o" | "output" => {
+                let output: Vec<String> = self
+                    .state
+                    .drain_output()
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect();
+                lines.push(output.join(","));
+            }
+            "save" => {
+                self.state
+                    .save_snapshot(arg(0)?)
+                    .map_err(|e| e.to_string())?;
+            }
+            "load" => {
+                self.state = ProgramState::load_snapshot(arg(0)?)
+                    .map_err(|e| e.to_string())?;
+                lines.push(self.current());
+            }
+            "" => (),
+            "h" | "help" => lines.push(HELP.to_string()),
+            _ => {
+                return Err(format!("Unknown command {:?}, try help", command))
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Runs one debugger command and returns the lines to show for it, or
+    /// `None` if the user asked to quit.
+    pub fn execute(&mut self, line: &str) -> Option<Vec<String>> {
+        match line.trim() {
+            "q" | "quit" => None,
+            line => Some(
+                self.commands(line).unwrap_or_else(|message| vec![message]),
+            ),
+        }
+    }
+
+    /// Reads commands from `input` until it runs out or the user quits.  An
+    /// empty line repeats the previous command.
+    pub fn repl<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        mut output: W,
+    ) -> io::Result<()> {
+        writeln!(output, "{}", self.current())?;
+        write!(output, "(debug) ")?;
+        output.flush()?;
+
+        let mut previous = String::new();
+        for line in input.lines() {
+            let mut line = line?;
+            if line.trim().is_empty() {
+                line = previous.clone();
+            }
+            match self.execute(&line) {
+                Some(lines) => {
+                    for text in lines {
+                        writeln!(output, "{}", text)?;
+                    }
+                }
+                None => return Ok(()),
+            }
+            previous = line;
+            write!(output, "(debug) ")?;
+            output.flush()?;
+        }
+        writeln!(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Reads a number, then counts it down to zero outputting every step
+    const COUNTDOWN: [i64; 13] =
+        [3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = Debugger::new(ProgramState::new(&COUNTDOWN));
+        let mut outputs = vec![];
+        assert_eq!(debugger.cont(&mut outputs), Event::NeedInput);
+        assert_eq!(debugger.state.status, ProgramStatus::WaitingForInput);
+
+        debugger.state.push_input(3);
+        debugger.breakpoints.insert(8);
+        assert_eq!(debugger.cont(&mut outputs), Event::Breakpoint(8));
+        assert_eq!(outputs, vec![3]);
+
+        debugger.breakpoints.clear();
+        debugger.watchpoints.insert(12);
+        assert_eq!(
+            debugger.cont(&mut outputs),
+            Event::Watchpoint {
+                address: 12,
+                old: 2,
+                new: 1
+            }
+        );
+        assert_eq!(debugger.state.ic, 8);
+
+        debugger.watchpoints.clear();
+        assert_eq!(debugger.cont(&mut outputs), Event::Halted);
+        assert_eq!(outputs, vec![3, 2, 1]);
+        assert_eq!(debugger.state.output, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut debugger = Debugger::new(ProgramState::new(&COUNTDOWN));
+        let mut run = |line: &str| debugger.execute(line).unwrap().join("\n");
+
+        assert_eq!(
+            run("list 0 3"),
+            [
+                "=>      0  IN -> [12]",
+                "        2  OUT [12]",
+                "        4  ADD [12], #-1 -> [12]",
+            ]
+            .join("\n")
+        );
+        assert_eq!(run("i 2"), "queued 1 value(s), 1 waiting");
+        assert_eq!(run("b 11"), "breakpoint at 11");
+        assert_eq!(
+            run("step 2"),
+            [
+                "=>      0  IN -> [12]",
+                "=>      2  OUT [12]",
+                "output 2",
+                "=>      4  ADD [12], #-1 -> [12]",
+            ]
+            .join("\n")
+        );
+        assert_eq!(run("mem 10 3"), "      10:       2      99       2");
+        assert_eq!(
+            run("c"),
+            ["output 1", "breakpoint at 11", "=>     11  HLT"].join("\n")
+        );
+        assert_eq!(
+            run("regs").lines().next(),
+            Some("ic 11  relative_base 0  status Running  cycles 7  input []")
+        );
+        assert_eq!(run("o"), "2,1");
+        assert_eq!(run("d 11"), "");
+        assert_eq!(run("d 11"), "Nothing set at 11");
+        assert_eq!(run("s"), "=>     11  HLT\nhalted");
+        assert_eq!(run("bogus"), "Unknown command \"bogus\", try help");
+        assert_eq!(debugger.execute("quit"), None);
+    }
+
+    #[test]
+    fn test_repl() {
+        let script = "input 1\nwatch 12\nc\n\n";
+        let mut debugger = Debugger::new(ProgramState::new(&COUNTDOWN));
+        let mut output = vec![];
+        debugger.repl(script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("watchpoint [12]: 0 -> 1"));
+        assert!(output.contains("watchpoint [12]: 1 -> 0"));
+        assert!(output.contains("output 1"));
+    }
+}
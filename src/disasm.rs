@@ -0,0 +1,267 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::computer::{decode, Instruction, Mode, Opcode};
+
+/// Decodes the instruction at `address` if it looks like real code: the
+/// opcode and modes are valid, written in canonical form, the parameters fit
+/// inside the program and nothing writes through an immediate.
+pub fn decode_at(
+    program: &[i64],
+    address: usize,
+) -> Option<(Instruction, &[i64])> {
+    let raw = *program.get(address)?;
+    let inst = decode(raw, address).ok()?;
+    if inst.encode() != raw || address + inst.len() > program.len() {
+        return None;
+    }
+    let n = inst.opcode.n_params();
+    if inst.opcode.writes() && inst.modes[n - 1] == Mode::Immediate {
+        return None;
+    }
+    Some((inst, &program[address + 1..address + inst.len()]))
+}
+
+fn jump_target(inst: &Instruction, params: &[i64]) -> Option<usize> {
+    match inst.opcode {
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+            if inst.modes[1] == Mode::Immediate && params[1] >= 0 {
+                Some(params[1] as usize)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn always_jumps(inst: &Instruction, params: &[i64]) -> bool {
+    inst.modes[0] == Mode::Immediate
+        && match inst.opcode {
+            Opcode::JumpIfTrue => params[0] != 0,
+            Opcode::JumpIfFalse => params[0] == 0,
+            _ => false,
+        }
+}
+
+/// Splits a program into code and data by following control flow from
+/// address 0.  Returns the start address of every instruction found along
+/// with the addresses that deserve a label.
+///
+/// Jumps through memory can't be followed statically, so as a heuristic the
+/// instruction after a halt or unconditional jump is also treated as code if
+/// some reachable instruction uses its address as an immediate, which is how
+/// Intcode programs push return addresses.
+pub fn find_code(program: &[i64]) -> (BTreeSet<usize>, BTreeSet<usize>) {
+    let mut code = BTreeSet::new();
+    let mut labels = BTreeSet::new();
+    let mut constants = HashSet::new();
+    let mut dead_ends = vec![];
+    let mut tried = HashSet::new();
+    let mut pending = vec![0];
+
+    loop {
+        while let Some(address) = pending.pop() {
+            if code.contains(&address) {
+                continue;
+            }
+            let (inst, params) = match decode_at(program, address) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+            code.insert(address);
+
+            for (mode, param) in inst.modes.iter().zip(params) {
+                if *mode == Mode::Immediate {
+                    constants.insert(*param);
+                }
+            }
+
+            if let Some(target) = jump_target(&inst, params) {
+                labels.insert(target);
+                pending.push(target);
+            }
+
+            let next = address + inst.len();
+            if inst.opcode == Opcode::Halt || always_jumps(&inst, params) {
+                dead_ends.push(next);
+            } else {
+                pending.push(next);
+            }
+        }
+
+        let returns: Vec<usize> = dead_ends
+            .iter()
+            .filter(|a| !tried.contains(*a))
+            .filter(|a| constants.contains(&(**a as i64)))
+            .cloned()
+            .collect();
+        if returns.is_empty() {
+            break;
+        }
+        for address in returns {
+            tried.insert(address);
+            if !code.contains(&address) {
+                labels.insert(address);
+                pending.push(address);
+            }
+        }
+    }
+
+    (code, labels)
+}
+
+pub fn label(address: usize) -> String {
+    format!("L{}", address)
+}
+
+fn format_operand(mode: Mode, value: i64, labels: &BTreeSet<usize>) -> String {
+    match mode {
+        Mode::Immediate => {
+            if value >= 0 && labels.contains(&(value as usize)) {
+                format!("#{}", label(value as usize))
+            } else {
+                format!("#{}", value)
+            }
+        }
+        Mode::Position => format!("[{}]", value),
+        Mode::Relative => {
+            if value < 0 {
+                format!("[rb{}]", value)
+            } else {
+                format!("[rb+{}]", value)
+            }
+        }
+    }
+}
+
+/// Formats one instruction as `MNEMONIC src, src -> dst`.  Immediate jump
+/// targets found in `labels` are printed by name.
+pub fn format_instruction(
+    inst: &Instruction,
+    params: &[i64],
+    labels: &BTreeSet<usize>,
+) -> String {
+    let n = inst.opcode.n_params();
+    let no_labels = BTreeSet::new();
+    let mut sources = vec![];
+    let mut destination = None;
+    for (i, (mode, param)) in inst.modes.iter().zip(params).enumerate() {
+        if inst.opcode.writes() && i == n - 1 {
+            destination = Some(format_operand(*mode, *param, &no_labels));
+        } else if jump_target(inst, params).is_some() && i == 1 {
+            sources.push(format_operand(*mode, *param, labels));
+        } else {
+            sources.push(format_operand(*mode, *param, &no_labels));
+        }
+    }
+
+    let mut text = inst.opcode.mnemonic().to_string();
+    if !sources.is_empty() {
+        text = format!("{} {}", text, sources.join(", "));
+    }
+    if let Some(destination) = destination {
+        text = format!("{} -> {}", text, destination);
+    }
+    text
+}
+
+fn listing_line(address: usize, text: &str) -> String {
+    format!("    {:<36}; {}", text, address)
+}
+
+fn flush_data(lines: &mut Vec<String>, data: &mut Vec<i64>, address: usize) {
+    if !data.is_empty() {
+        let values: Vec<String> = data.iter().map(|x| x.to_string()).collect();
+        let text = format!(".data {}", values.join(", "));
+        lines.push(listing_line(address - data.len(), &text));
+        data.clear();
+    }
+}
+
+/// Disassembles a whole program into a listing with addresses in trailing
+/// comments, jump targets as labels and everything unreachable as `.data`.
+pub fn listing(program: &[i64]) -> String {
+    let (code, labels) = find_code(program);
+    let mut lines = vec![];
+    let mut data = vec![];
+
+    let mut address = 0;
+    while address < program.len() {
+        if labels.contains(&address) {
+            flush_data(&mut lines, &mut data, address);
+            lines.push(format!("{}:", label(address)));
+        }
+
+        if code.contains(&address) {
+            let (inst, params) = decode_at(program, address).unwrap();
+
+            // Don't swallow a label or another instruction that starts
+            // partway through this one
+            let end = address + inst.len();
+            let overlaps = (address + 1..end)
+                .any(|a| labels.contains(&a) || code.contains(&a));
+            if !overlaps {
+                flush_data(&mut lines, &mut data, address);
+                let text = format_instruction(&inst, params, &labels);
+                lines.push(listing_line(address, &text));
+                address = end;
+                continue;
+            }
+        }
+
+        data.push(program[address]);
+        if data.len() == 8 {
+            flush_data(&mut lines, &mut data, address + 1);
+        }
+        address += 1;
+    }
+    flush_data(&mut lines, &mut data, address);
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_listing() {
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let expected = [
+            "    IN -> [9]                           ; 0",
+            "    EQ [9], [10] -> [9]                 ; 2",
+            "    OUT [9]                             ; 6",
+            "    HLT                                 ; 8",
+            "    .data -1, 8                         ; 9",
+        ];
+        assert_eq!(listing(&program), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_labels() {
+        // Calls a subroutine at 9 that outputs 5 and returns to 7
+        let program =
+            vec![21101, 7, 0, 0, 1105, 1, 9, 99, 0, 104, 5, 2106, 0, 0];
+        let (code, labels) = find_code(&program);
+        assert_eq!(code.into_iter().collect::<Vec<_>>(), vec![0, 4, 7, 9, 11]);
+        assert_eq!(labels.into_iter().collect::<Vec<_>>(), vec![7, 9]);
+
+        let lines: Vec<String> = listing(&program)
+            .lines()
+            .map(|line| line.split(';').next().unwrap().trim().to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "ADD #7, #0 -> [rb+0]",
+                "JT #1, #L9",
+                "L7:",
+                "HLT",
+                ".data 0",
+                "L9:",
+                "OUT #5",
+                "JF #0, [rb+0]",
+            ]
+        );
+    }
+}
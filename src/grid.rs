@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::ops::{Add, Sub};
+
+/// A position on a grid, with `y` growing downwards as on screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    pub fn step(self, direction: Direction) -> Point {
+        self + direction.delta()
+    }
+
+    /// The four points sharing an edge with this one.
+    pub fn neighbours(self) -> impl Iterator<Item = Point> {
+        Direction::ALL.iter().map(move |d| self.step(*d))
+    }
+
+    pub fn manhattan(self, other: Point) -> i32 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl From<(i32, i32)> for Point {
+    fn from((x, y): (i32, i32)) -> Point {
+        Point::new(x, y)
+    }
+}
+
+impl From<Point> for (i32, i32) {
+    fn from(point: Point) -> (i32, i32) {
+        (point.x, point.y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    /// Clockwise from north.
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub fn turn_right(self) -> Direction {
+        Direction::ALL[(self as usize + 1) % 4]
+    }
+
+    pub fn turn_left(self) -> Direction {
+        Direction::ALL[(self as usize + 3) % 4]
+    }
+
+    pub fn opposite(self) -> Direction {
+        Direction::ALL[(self as usize + 2) % 4]
+    }
+
+    pub fn delta(self) -> Point {
+        match self {
+            Direction::North => Point::new(0, -1),
+            Direction::East => Point::new(1, 0),
+            Direction::South => Point::new(0, 1),
+            Direction::West => Point::new(-1, 0),
+        }
+    }
+}
+
+/// The smallest rectangle holding a set of points, corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn of<I: IntoIterator<Item = Point>>(points: I) -> Option<Bounds> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(
+            Bounds {
+                min: first,
+                max: first,
+            },
+            |bounds, p| Bounds {
+                min: Point::new(bounds.min.x.min(p.x), bounds.min.y.min(p.y)),
+                max: Point::new(bounds.max.x.max(p.x), bounds.max.y.max(p.y)),
+            },
+        ))
+    }
+
+    pub fn width(&self) -> i32 {
+        self.max.x - self.min.x + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.max.y - self.min.y + 1
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Storage<T> {
+    Dense {
+        width: usize,
+        height: usize,
+        cells: Vec<Option<T>>,
+    },
+    Sparse(HashMap<Point, T>),
+}
+
+/// Values at points on a grid.  A dense grid covers a fixed rectangle from
+/// the origin, and suits maps parsed from text.  A sparse grid grows in any
+/// direction, and suits maps that are explored as they're drawn.  Either
+/// way, points can be empty.
+#[derive(Debug, Clone)]
+pub struct Grid<T> {
+    storage: Storage<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn dense(width: usize, height: usize) -> Grid<T> {
+        let mut cells = Vec::with_capacity(width * height);
+        cells.resize_with(width * height, || None);
+        Grid {
+            storage: Storage::Dense {
+                width,
+                height,
+                cells,
+            },
+        }
+    }
+
+    pub fn sparse() -> Grid<T> {
+        Grid {
+            storage: Storage::Sparse(HashMap::new()),
+        }
+    }
+
+    /// Reads a map drawn in characters, one row per line, into a dense
+    /// grid.  Characters `tile` returns `None` for are left empty.
+    pub fn parse(
+        text: &str,
+        mut tile: impl FnMut(char) -> Option<T>,
+    ) -> Grid<T> {
+        let lines: Vec<&str> = text.lines().collect();
+        let width = lines.iter().map(|line| line.chars().count()).max();
+        let mut grid = Grid::dense(width.unwrap_or(0), lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if let Some(value) = tile(c) {
+                    grid.insert(Point::new(x as i32, y as i32), value);
+                }
+            }
+        }
+        grid
+    }
+
+    fn index(width: usize, height: usize, point: Point) -> Option<usize> {
+        if (0..width as i32).contains(&point.x)
+            && (0..height as i32).contains(&point.y)
+        {
+            Some(point.y as usize * width + point.x as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        match &self.storage {
+            Storage::Dense {
+                width,
+                height,
+                cells,
+            } => Grid::<T>::index(*width, *height, point)
+                .and_then(|i| cells[i].as_ref()),
+            Storage::Sparse(cells) => cells.get(&point),
+        }
+    }
+
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        match &mut self.storage {
+            Storage::Dense {
+                width,
+                height,
+                cells,
+            } => Grid::<T>::index(*width, *height, point)
+                .and_then(move |i| cells[i].as_mut()),
+            Storage::Sparse(cells) => cells.get_mut(&point),
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.get(point).is_some()
+    }
+
+    /// Sets the value at `point`, returning the old one.  Panics if a
+    /// dense grid doesn't cover the point.
+    pub fn insert(&mut self, point: Point, value: T) -> Option<T> {
+        match &mut self.storage {
+            Storage::Dense {
+                width,
+                height,
+                cells,
+            } => {
+                let i = Grid::<T>::index(*width, *height, point)
+                    .unwrap_or_else(|| panic!("{:?} is off the grid", point));
+                cells[i].replace(value)
+            }
+            Storage::Sparse(cells) => cells.insert(point, value),
+        }
+    }
+
+    /// Every point with a value, in no particular order.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Point, &T)> + '_> {
+        match &self.storage {
+            Storage::Dense { width, cells, .. } => {
+                let width = *width;
+                Box::new(cells.iter().enumerate().filter_map(move |(i, c)| {
+                    let point =
+                        Point::new((i % width) as i32, (i / width) as i32);
+                    c.as_ref().map(|value| (point, value))
+                }))
+            }
+            Storage::Sparse(cells) => {
+                Box::new(cells.iter().map(|(point, value)| (*point, value)))
+            }
+        }
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        self.iter().map(|(point, _)| point)
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// The rectangle around every point with a value.
+    pub fn bounds(&self) -> Option<Bounds> {
+        Bounds::of(self.points())
+    }
+
+    /// The edge-sharing neighbours of `point` that have values.
+    pub fn neighbours(
+        &self,
+        point: Point,
+    ) -> impl Iterator<Item = (Point, &T)> + '_ {
+        point
+            .neighbours()
+            .filter_map(move |p| self.get(p).map(|value| (p, value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_direction() {
+        let north = Direction::North;
+        assert_eq!(north.turn_right(), Direction::East);
+        assert_eq!(north.turn_left(), Direction::West);
+        assert_eq!(north.opposite(), Direction::South);
+        assert_eq!(north.turn_left().turn_left(), north.opposite());
+        assert_eq!(Point::new(2, 3).step(north), Point::new(2, 2));
+        assert_eq!(Point::new(0, 0).neighbours().count(), 4);
+    }
+
+    #[test]
+    fn test_grid() {
+        let text = "#.#\n..\n#";
+        let tile = |c| if c == '#' { Some(c) } else { None };
+        let dense = Grid::parse(text, tile);
+        assert_eq!(dense.len(), 3);
+        assert_eq!(dense.get(Point::new(2, 0)), Some(&'#'));
+        assert_eq!(dense.get(Point::new(1, 0)), None);
+        assert_eq!(dense.get(Point::new(-1, 0)), None);
+        assert_eq!(dense.neighbours(Point::new(0, 1)).count(), 2);
+
+        let mut sparse = Grid::sparse();
+        for (point, value) in dense.iter() {
+            sparse.insert(point - Point::new(5, 5), *value);
+        }
+        assert_eq!(
+            sparse.bounds(),
+            Some(Bounds {
+                min: Point::new(-5, -5),
+                max: Point::new(-3, -3),
+            })
+        );
+        assert_eq!(sparse.bounds().unwrap().width(), 3);
+        assert_eq!(sparse.bounds().unwrap().height(), 3);
+    }
+}
@@ -1,10 +1,22 @@
 extern crate clap;
 
 use std::thread;
+use std::time::Duration;
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
+mod ascii;
+mod asm;
 mod computer;
+mod debugger;
+mod disasm;
+mod grid;
+mod memory;
+mod pathfind;
+mod profile;
+mod render;
+mod threaded;
+mod trace;
 
 mod day01;
 mod day02;
@@ -23,123 +35,484 @@ mod day14;
 mod day15;
 mod day16;
 mod day17;
-
+mod day18;
 mod day19;
 mod day20;
+mod day21;
+mod day22;
+mod day23;
+mod day24;
+mod day25;
+
+// Queues the values in the file given with `--input`, if any.  Returns false
+// if the file couldn't be used.
+fn queue_input(
+    state: &mut computer::ProgramState,
+    matches: &ArgMatches,
+) -> bool {
+    let name = match matches.value_of("input") {
+        Some(name) => name,
+        None => return true,
+    };
+    let values = std::fs::read_to_string(name)
+        .map_err(|e| e.to_string())
+        .and_then(|text| debugger::parse_values(&text));
+    match values {
+        Ok(values) => {
+            state.push_inputs(values);
+            true
+        }
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            false
+        }
+    }
+}
+
+// Draws a day's puzzle with `renderer`, waiting at the end if there's anyone
+// watching.  Returns the renderer, for anything it kept.
+fn render_day<R: render::Renderer + Send + 'static>(
+    day: u32,
+    mut renderer: R,
+) -> R {
+    match day {
+        8 => {
+            let layers = day08::to_layers(&day08::load_input(), 25, 6);
+            day08::print_layer(&day08::flatten_layers(&layers), &mut renderer);
+        }
+        11 => {
+            let panel = day11::paint(&day11::load_input(), 1);
+            day11::print_panel(&panel, &mut renderer);
+        }
+        13 => {
+            let input = day13::load_input("inputs/13.txt");
+            day13::play(&input, Some(&mut renderer));
+        }
+        15 => {
+            let input = day15::load_input("inputs/15.txt");
+            day15::explore_entire_map(&input, Some(&mut renderer));
+        }
+        17 => {
+            let map = day17::parse_input(&day17::load_input("inputs/17.txt"));
+            day17::render(&map, &mut renderer);
+        }
+        19 => {
+            let map = day19::scan(&day19::load_input("inputs/19.txt"), 50);
+            day19::render(&map, &mut renderer);
+        }
+        20 => {
+            let input = day20::load_input("inputs/20.txt");
+            renderer = day20::animate(&input, renderer);
+        }
+        _ => eprintln!("Day {} has nothing to render", day),
+    }
+    renderer.pause();
+    renderer
+}
+
+// Runs a day on a thread of its own for the `day 0` runner, so one that
+// panics doesn't stop the others
+fn spawn_day<F: FnOnce() + Send + 'static>(
+    day: u32,
+    f: F,
+) -> (u32, thread::JoinHandle<()>) {
+    (day, thread::spawn(f))
+}
 
 fn main() {
     let matches = App::new("AOC2019")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("DAY")
                 .required(true)
                 .index(1)
                 .help("Day number to run"),
         )
+        .subcommand(
+            SubCommand::with_name("asm")
+                .about("Assemble Intcode mnemonics into a program")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .index(1)
+                        .help("Intcode assembly source"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("debug")
+                .about("Step through an Intcode program interactively")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .index(1)
+                        .help("Comma separated Intcode program"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .help("File of values to queue as input"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("play")
+                .about("Play the day 25 text adventure from stdin")
+                .arg(
+                    Arg::with_name("FILE")
+                        .default_value("inputs/25.txt")
+                        .index(1)
+                        .help("Comma separated Intcode program"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("profile")
+                .about("Count where an Intcode program spends its time")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .index(1)
+                        .help("Comma separated Intcode program"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .help("File of values to queue as input"),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .takes_value(true)
+                        .help("Rows to show in each table, default 10"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("trace")
+                .about("Record every instruction an Intcode program executes")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .index(1)
+                        .help("Comma separated Intcode program"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .help("File of values to queue as input"),
+                )
+                .arg(
+                    Arg::with_name("save")
+                        .long("save")
+                        .takes_value(true)
+                        .help("Write a compact trace file instead of text"),
+                )
+                .arg(
+                    Arg::with_name("dump")
+                        .long("dump")
+                        .help("Print FILE as a previously saved trace"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("render")
+                .about("Draw a day's puzzle: 8, 11, 13, 15, 17, 19 or 20")
+                .arg(
+                    Arg::with_name("DAY")
+                        .required(true)
+                        .index(1)
+                        .help("Day number to draw"),
+                )
+                .arg(
+                    Arg::with_name("backend")
+                        .long("backend")
+                        .takes_value(true)
+                        .possible_values(&["ansi", "ncurses", "text"])
+                        .default_value("ansi")
+                        .help("Where to draw; text prints the last frame"),
+                )
+                .arg(
+                    Arg::with_name("delay")
+                        .long("delay")
+                        .takes_value(true)
+                        .default_value("33")
+                        .help("Milliseconds to hold each frame"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("spring")
+                .about("Search for a springscript that crosses every HULL")
+                .arg(
+                    Arg::with_name("HULL")
+                        .required(true)
+                        .multiple(true)
+                        .index(1)
+                        .help("Hull the droid fell into, like #####.#..####"),
+                )
+                .arg(
+                    Arg::with_name("run")
+                        .long("run")
+                        .help("Search RUN scripts instead of WALK"),
+                )
+                .arg(
+                    Arg::with_name("max-len")
+                        .long("max-len")
+                        .takes_value(true)
+                        .default_value("4")
+                        .help("Most instructions to try"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("disasm")
+                .about("Disassemble an Intcode program")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .index(1)
+                        .help("Comma separated Intcode program"),
+                ),
+        )
         .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("asm") {
+        let name = matches.value_of("FILE").unwrap();
+        let source = std::fs::read_to_string(name).unwrap();
+        match asm::assemble(&source) {
+            Ok(program) => println!(
+                "{}",
+                program
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Err(e) => eprintln!("{}: {}", name, e),
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("debug") {
+        let program = computer::load_program(matches.value_of("FILE").unwrap());
+        let mut state = computer::ProgramState::new(&program);
+        if !queue_input(&mut state, matches) {
+            return;
+        }
+        let mut debugger = debugger::Debugger::new(state);
+        let stdin = std::io::stdin();
+        debugger.repl(stdin.lock(), std::io::stdout()).unwrap();
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("play") {
+        let program = day25::load_input(matches.value_of("FILE").unwrap());
+        let stdin = std::io::stdin();
+        day25::play(&program, stdin.lock(), std::io::stdout()).unwrap();
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("profile") {
+        let program = computer::load_program(matches.value_of("FILE").unwrap());
+        let mut state = computer::ProgramState::new(&program);
+        if !queue_input(&mut state, matches) {
+            return;
+        }
+        let top = matches.value_of("top").map_or(10, |n| n.parse().unwrap());
+        state.profile = Some(profile::Profile::default());
+        if let Err(e) = state.try_run_until_blocked() {
+            eprintln!("{}", e);
+        }
+        print!("{}", state.profile.unwrap().report(top));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("trace") {
+        let name = matches.value_of("FILE").unwrap();
+        if matches.is_present("dump") {
+            match trace::Trace::load(name) {
+                Ok(trace) => print!("{}", trace.dump()),
+                Err(e) => eprintln!("{}: {}", name, e),
+            }
+            return;
+        }
+
+        let mut state =
+            computer::ProgramState::new(&computer::load_program(name));
+        if !queue_input(&mut state, matches) {
+            return;
+        }
+        state.trace = Some(trace::Trace::default());
+        if let Err(e) = state.try_run_until_blocked() {
+            eprintln!("{}", e);
+        }
+
+        let trace = state.trace.unwrap();
+        match matches.value_of("save") {
+            Some(path) => trace.save(path).unwrap(),
+            None => print!("{}", trace.dump()),
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("render") {
+        let day = matches.value_of("DAY").unwrap().parse().unwrap();
+        let delay = matches.value_of("delay").unwrap().parse().unwrap();
+        let delay = Duration::from_millis(delay);
+        match matches.value_of("backend").unwrap() {
+            "ncurses" => {
+                render_day(day, render::Ncurses::new(delay));
+            }
+            "text" => {
+                let frames = render_day(day, render::FrameBuffer::new()).frames;
+                if let Some(frame) = frames.last() {
+                    println!("{}", frame);
+                }
+            }
+            _ => {
+                render_day(day, render::Ansi::stdout(delay));
+            }
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("spring") {
+        let hulls: Vec<&str> = matches.values_of("HULL").unwrap().collect();
+        let mode = if matches.is_present("run") {
+            day21::Mode::Run
+        } else {
+            day21::Mode::Walk
+        };
+        let max_len = matches.value_of("max-len").unwrap().parse().unwrap();
+        match day21::search(&hulls, mode, max_len) {
+            Some(script) => println!("{}", script.lines().join("\n")),
+            None => eprintln!(
+                "No script of at most {} instructions crosses every hull",
+                max_len
+            ),
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("disasm") {
+        let program = computer::load_program(matches.value_of("FILE").unwrap());
+        print!("{}", disasm::listing(&program));
+        return;
+    }
+
     let day = matches.value_of("DAY").unwrap().parse().unwrap();
 
     match day {
         0 => {
             let mut threads = Vec::with_capacity(25);
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(1, || {
                 let input = day01::load_input();
                 println!("Day 1 Part 1 Solution {:?}", day01::part1(&input));
                 println!("Day 1 Part 2 Solution {:?}", day01::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(2, || {
                 let input = day02::load_input();
                 println!("Day 2 Part 1 Solution {:?}", day02::part1(&input));
                 println!("Day 2 Part 2 Solution {:?}", day02::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(3, || {
                 let input = day03::load_input();
                 println!("Day 3 Part 1 Solution {:?}", day03::part1(&input));
                 println!("Day 3 Part 2 Solution {:?}", day03::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(4, || {
                 let input = day04::load_input();
                 println!("Day 4 Part 1 Solution {:?}", day04::part1(&input));
                 println!("Day 4 Part 2 Solution {:?}", day04::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(5, || {
                 let input = day05::load_input();
                 println!("Day 5 Part 1 Solution {:?}", day05::part1(&input));
                 println!("Day 5 Part 2 Solution {:?}", day05::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(6, || {
                 let input = day06::load_input("inputs/06.txt");
                 println!("Day 6 Part 1 Solution {:?}", day06::part1(&input));
                 println!("Day 6 Part 2 Solution {:?}", day06::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(7, || {
                 let input = day07::load_input();
                 println!("Day 7 Part 1 Solution {:?}", day07::part1(&input));
                 println!("Day 7 Part 2 Solution {:?}", day07::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(8, || {
                 let input = day08::load_input();
                 println!("Day 8 Part 1 Solution {:?}", day08::part1(&input));
                 println!("Day 8 Part 2 Solution {}", day08::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(9, || {
                 let input = day09::load_input();
                 println!("Day 9 Part 1 Solution {:?}", day09::part1(&input));
                 println!("Day 9 Part 2 Solution {:?}", day09::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(10, || {
                 let input = day10::load_input("inputs/10.txt");
                 println!("Day 10 Part 1 Solution {:?}", day10::part1(&input));
                 println!("Day 10 Part 2 Solution {:?}", day10::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(11, || {
                 let input = day11::load_input();
                 println!("Day 11 Part 1 Solution {:?}", day11::part1(&input));
                 println!("Day 11 Part 2 Solution {}", day11::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(12, || {
                 let input = day12::load_input("inputs/12.txt");
                 println!("Day 12 Part 1 Solution {:?}", day12::part1(&input));
                 println!("Day 12 Part 2 Solution {:?}", day12::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(13, || {
                 let input = day13::load_input("inputs/13.txt");
                 println!("Day 13 Part 1 Solution {:?}", day13::part1(&input));
                 println!("Day 13 Part 2 Solution {:?}", day13::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(14, || {
                 let input = day14::load_input("inputs/14.txt");
                 println!("Day 14 Part 1 Solution {:?}", day14::part1(&input));
                 println!("Day 14 Part 2 Solution {:?}", day14::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(15, || {
                 let input = day15::load_input("inputs/15.txt");
                 println!("Day 15 Part 1 Solution {:?}", day15::part1(&input));
                 println!("Day 15 Part 2 Solution {:?}", day15::part2(&input));
             }));
 
-            threads.push(thread::spawn(|| {
+            threads.push(spawn_day(18, || {
+                let input = day18::load_input("inputs/18.txt");
+                println!("Day 18 Part 1 Solution {:?}", day18::part1(&input));
+                println!("Day 18 Part 2 Solution {:?}", day18::part2(&input));
+            }));
+
+            threads.push(spawn_day(19, || {
                 let input = day19::load_input("inputs/19.txt");
                 println!("Day 19 Part 1 Solution {:?}", day19::part1(&input));
                 println!("Day 19 Part 2 Solution {:?}", day19::part2(&input));
             }));
 
-            for t in threads {
-                t.join().unwrap();
+            // A failing day has already printed its panic, so collect the
+            // rest before saying which went wrong
+            let failed: Vec<u32> = threads
+                .into_iter()
+                .filter_map(|(day, t)| t.join().err().map(|_| day))
+                .collect();
+            if !failed.is_empty() {
+                eprintln!("Failed days: {:?}", failed);
+                std::process::exit(1);
             }
         }
         1 => {
@@ -227,6 +600,11 @@ fn main() {
             println!("Part 1 Solution {:?}", day17::part1(&input));
             println!("Part 2 Solution {:?}", day17::part2(&input));
         }
+        18 => {
+            let input = day18::load_input("inputs/18.txt");
+            println!("Part 1 Solution {:?}", day18::part1(&input));
+            println!("Part 2 Solution {:?}", day18::part2(&input));
+        }
         19 => {
             let input = day19::load_input("inputs/19.txt");
             println!("Part 1 Solution {:?}", day19::part1(&input));
@@ -237,6 +615,30 @@ fn main() {
             println!("Part 1 Solution {:?}", day20::part1(&input));
             println!("Part 2 Solution {:?}", day20::part2(&input));
         }
+        21 => {
+            let input = day21::load_input("inputs/21.txt");
+            println!("Part 1 Solution {:?}", day21::part1(&input));
+            println!("Part 2 Solution {:?}", day21::part2(&input));
+        }
+        22 => {
+            let input = day22::load_input("inputs/22.txt");
+            println!("Part 1 Solution {:?}", day22::part1(&input));
+            println!("Part 2 Solution {:?}", day22::part2(&input));
+        }
+        23 => {
+            let input = day23::load_input("inputs/23.txt");
+            println!("Part 1 Solution {:?}", day23::part1(&input));
+            println!("Part 2 Solution {:?}", day23::part2(&input));
+        }
+        24 => {
+            let input = day24::load_input("inputs/24.txt");
+            println!("Part 1 Solution {:?}", day24::part1(&input));
+            println!("Part 2 Solution {:?}", day24::part2(&input));
+        }
+        25 => {
+            let input = day25::load_input("inputs/25.txt");
+            println!("Part 1 Solution {:?}", day25::part1(&input));
+        }
         _ => println!("Day {} not yet implemented", day),
     }
 }
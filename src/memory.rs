@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+pub const PAGE_SIZE: usize = 512;
+
+type Page = Arc<[i64; PAGE_SIZE]>;
+
+/// Intcode memory where every non-negative address is readable and writable,
+/// defaulting to zero.
+///
+/// The program image lives in a dense run of pages and anything written past
+/// it goes into sparse pages keyed by page number.  Pages are reference
+/// counted and copied on write, so cloning a `Memory` only bumps counts.
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    dense: Vec<Page>,
+    sparse: HashMap<usize, Page>,
+}
+
+impl Memory {
+    pub fn new(program: &[i64]) -> Memory {
+        let dense = program
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Arc::new(page)
+            })
+            .collect();
+        Memory {
+            dense,
+            sparse: HashMap::new(),
+        }
+    }
+
+    /// Every backed page as its start address and contents, in address
+    /// order.
+    pub fn pages(&self) -> Vec<(usize, &[i64])> {
+        let mut sparse: Vec<_> = self.sparse.iter().collect();
+        sparse.sort_by_key(|(number, _)| **number);
+
+        self.dense
+            .iter()
+            .enumerate()
+            .chain(sparse.into_iter().map(|(number, page)| (*number, page)))
+            .map(|(number, page)| (number * PAGE_SIZE, &page[..]))
+            .collect()
+    }
+
+    fn page(&self, number: usize) -> Option<&Page> {
+        if number < self.dense.len() {
+            Some(&self.dense[number])
+        } else {
+            self.sparse.get(&number)
+        }
+    }
+
+    fn page_mut(&mut self, number: usize) -> &mut [i64; PAGE_SIZE] {
+        let page = if number < self.dense.len() {
+            &mut self.dense[number]
+        } else {
+            self.sparse
+                .entry(number)
+                .or_insert_with(|| Arc::new([0; PAGE_SIZE]))
+        };
+        Arc::make_mut(page)
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i64;
+
+    fn index(&self, address: usize) -> &i64 {
+        match self.page(address / PAGE_SIZE) {
+            Some(page) => &page[address % PAGE_SIZE],
+            None => &0,
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, address: usize) -> &mut i64 {
+        &mut self.page_mut(address / PAGE_SIZE)[address % PAGE_SIZE]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory() {
+        let program: Vec<i64> = (0..1000).collect();
+        let mut memory = Memory::new(&program);
+        assert_eq!(memory[999], 999);
+        assert_eq!(memory[1000], 0);
+        assert_eq!(memory[1 << 40], 0);
+
+        memory[1 << 40] = 7;
+        memory[3] = -3;
+        assert_eq!(memory[1 << 40], 7);
+        assert_eq!(memory[3], -3);
+
+        // Clones share pages until one side writes
+        let mut copy = memory.clone();
+        copy[3] = 33;
+        copy[1 << 40] = 77;
+        assert_eq!(memory[3], -3);
+        assert_eq!(memory[1 << 40], 7);
+        assert_eq!(copy[3], 33);
+        assert_eq!(copy[1 << 40], 77);
+    }
+}
@@ -0,0 +1,238 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// What a search found: the distance to every node it settled, the node
+/// each was reached from, and the goal, if it stopped at one.
+#[derive(Debug, Clone)]
+pub struct Search<N, C> {
+    pub distances: HashMap<N, C>,
+    pub predecessors: HashMap<N, N>,
+    pub goal: Option<N>,
+}
+
+impl<N: Eq + Hash + Clone, C: Copy> Search<N, C> {
+    fn new() -> Search<N, C> {
+        Search {
+            distances: HashMap::new(),
+            predecessors: HashMap::new(),
+            goal: None,
+        }
+    }
+
+    pub fn distance(&self, node: &N) -> Option<C> {
+        self.distances.get(node).cloned()
+    }
+
+    /// The nodes from the start to `node`, both included.
+    pub fn path_to(&self, node: &N) -> Option<Vec<N>> {
+        if !self.distances.contains_key(node) {
+            return None;
+        }
+        let mut path = vec![node.clone()];
+        while let Some(previous) = self.predecessors.get(path.last().unwrap()) {
+            path.push(previous.clone());
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn goal_distance(&self) -> Option<C> {
+        self.goal.as_ref().and_then(|goal| self.distance(goal))
+    }
+
+    pub fn goal_path(&self) -> Option<Vec<N>> {
+        self.goal.as_ref().and_then(|goal| self.path_to(goal))
+    }
+}
+
+/// Breadth first search, with every step costing one.  Stops at the first
+/// node `goal` accepts, or once everything reachable has been seen.
+pub fn bfs<N, I>(
+    start: N,
+    mut neighbours: impl FnMut(&N) -> I,
+    mut goal: impl FnMut(&N) -> bool,
+) -> Search<N, usize>
+where
+    N: Eq + Hash + Clone,
+    I: IntoIterator<Item = N>,
+{
+    let mut search = Search::new();
+    search.distances.insert(start.clone(), 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+
+    while let Some(node) = queue.pop_front() {
+        if goal(&node) {
+            search.goal = Some(node);
+            break;
+        }
+        let distance = search.distances[&node] + 1;
+        for next in neighbours(&node) {
+            if !search.distances.contains_key(&next) {
+                search.distances.insert(next.clone(), distance);
+                search.predecessors.insert(next.clone(), node.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+    search
+}
+
+/// Dijkstra's algorithm, where `cost` gives the price of stepping between
+/// two neighbours.  Stops at the first node `goal` accepts, or once
+/// everything reachable has been settled.
+pub fn dijkstra<N, C, I>(
+    start: N,
+    neighbours: impl FnMut(&N) -> I,
+    cost: impl FnMut(&N, &N) -> C,
+    goal: impl FnMut(&N) -> bool,
+) -> Search<N, C>
+where
+    N: Eq + Hash + Clone,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = N>,
+{
+    astar(start, neighbours, cost, |_| C::default(), goal)
+}
+
+/// A*, which is Dijkstra's algorithm steered towards the goal by
+/// `heuristic`.  The result is only the shortest path if the heuristic
+/// never overestimates the remaining cost.
+pub fn astar<N, C, I>(
+    start: N,
+    mut neighbours: impl FnMut(&N) -> I,
+    mut cost: impl FnMut(&N, &N) -> C,
+    mut heuristic: impl FnMut(&N) -> C,
+    mut goal: impl FnMut(&N) -> bool,
+) -> Search<N, C>
+where
+    N: Eq + Hash + Clone,
+    C: Copy + Ord + Default + Add<Output = C>,
+    I: IntoIterator<Item = N>,
+{
+    let mut search = Search::new();
+
+    // Nodes live in `nodes` so the heap only needs to order their indices
+    let mut nodes = vec![start.clone()];
+    let mut best: HashMap<N, (C, Option<N>)> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    best.insert(start.clone(), (C::default(), None));
+    heap.push(Reverse((heuristic(&start), Reverse(C::default()), 0)));
+
+    while let Some(Reverse((_, Reverse(distance), index))) = heap.pop() {
+        let node = nodes[index].clone();
+        if search.distances.contains_key(&node) || best[&node].0 < distance {
+            continue;
+        }
+        search.distances.insert(node.clone(), distance);
+        if let Some(previous) = best[&node].1.clone() {
+            search.predecessors.insert(node.clone(), previous);
+        }
+        if goal(&node) {
+            search.goal = Some(node);
+            break;
+        }
+
+        for next in neighbours(&node) {
+            if search.distances.contains_key(&next) {
+                continue;
+            }
+            let next_distance = distance + cost(&node, &next);
+            if best.get(&next).is_none_or(|(d, _)| next_distance < *d) {
+                best.insert(next.clone(), (next_distance, Some(node.clone())));
+                // Among equal priorities, try the furthest along first
+                let priority = next_distance + heuristic(&next);
+                let index = nodes.len();
+                heap.push(Reverse((priority, Reverse(next_distance), index)));
+                nodes.push(next);
+            }
+        }
+    }
+    search
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAZE: [&str; 6] = [
+        "#######", "#...#.#", "#.#.#.#", "#.#...#", "#####.#", "#######",
+    ];
+
+    fn open(point: &(usize, usize)) -> Vec<(usize, usize)> {
+        let (x, y) = *point;
+        vec![(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)]
+            .into_iter()
+            .filter(|&(x, y)| MAZE[y].as_bytes()[x] != b'#')
+            .collect()
+    }
+
+    #[test]
+    fn test_bfs() {
+        let search = bfs((1, 1), open, |_| false);
+        assert_eq!(search.goal, None);
+        assert_eq!(search.distances.len(), 12);
+        assert_eq!(search.distance(&(5, 4)), Some(7));
+        assert_eq!(search.distance(&(1, 3)), Some(2));
+
+        let search = bfs((1, 1), open, |&p| p == (5, 4));
+        assert_eq!(search.goal_distance(), Some(7));
+        assert_eq!(
+            search.goal_path(),
+            Some(vec![
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (3, 2),
+                (3, 3),
+                (4, 3),
+                (5, 3),
+                (5, 4)
+            ])
+        );
+        assert_eq!(search.path_to(&(0, 0)), None);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // The direct edge from a to c costs more than going round by b
+        let edges = |node: &char| match node {
+            'a' => vec!['b', 'c'],
+            'b' => vec!['c'],
+            'c' => vec!['d'],
+            _ => vec![],
+        };
+        let cost = |from: &char, to: &char| match (from, to) {
+            ('a', 'c') => 10,
+            _ => 3,
+        };
+        let search = dijkstra('a', edges, cost, |_| false);
+        assert_eq!(search.distance(&'c'), Some(6));
+        assert_eq!(search.distance(&'d'), Some(9));
+        assert_eq!(search.path_to(&'d'), Some(vec!['a', 'b', 'c', 'd']));
+    }
+
+    #[test]
+    fn test_astar() {
+        // Across an empty room, A* heads straight for the goal where
+        // Dijkstra spreads out in every direction
+        let room = |&(x, y): &(i32, i32)| {
+            vec![(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)]
+                .into_iter()
+                .filter(|&(x, y)| (0..10).contains(&x) && (0..10).contains(&y))
+                .collect::<Vec<_>>()
+        };
+        let goal = (9, 0);
+        let manhattan =
+            |&(x, y): &(i32, i32)| (x - goal.0).abs() + (y - goal.1).abs();
+
+        let search = astar((0, 0), room, |_, _| 1, manhattan, |&p| p == goal);
+        let flood = dijkstra((0, 0), room, |_, _| 1, |&p| p == goal);
+        assert_eq!(search.goal_distance(), Some(9));
+        assert_eq!(flood.goal_distance(), Some(9));
+        assert_eq!(search.goal_path().unwrap().len(), 10);
+        assert!(search.distances.len() < flood.distances.len());
+    }
+}
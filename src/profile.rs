@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::computer::{Instruction, Mode, Opcode};
+
+/// Execution counts gathered by `computer::step` while
+/// `ProgramState::profile` is set.  Instructions that block on input aren't
+/// counted until they actually run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub opcodes: HashMap<Opcode, u64>,
+    pub modes: HashMap<Mode, u64>,
+    pub instructions: HashMap<usize, u64>,
+    pub reads: HashMap<usize, u64>,
+    pub writes: HashMap<usize, u64>,
+    /// Instructions executed by each call to `run`, oldest first.
+    pub runs: Vec<u64>,
+}
+
+fn count<K: Eq + Hash>(counts: &mut HashMap<K, u64>, key: K) {
+    *counts.entry(key).or_insert(0) += 1;
+}
+
+// Top `n` rows of a frequency table, busiest first
+fn table<K: Ord + ToString>(
+    title: &str,
+    counts: &HashMap<K, u64>,
+    n: usize,
+) -> Vec<String> {
+    let total: u64 = counts.values().sum();
+    let mut rows: Vec<(&K, &u64)> = counts.iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+    let mut lines = vec![format!("{:<10} {:>12} {:>7}", title, "count", "%")];
+    for (key, count) in rows.into_iter().take(n) {
+        lines.push(format!(
+            "{:<10} {:>12} {:>6.2}%",
+            key.to_string(),
+            count,
+            100.0 * *count as f64 / total as f64
+        ));
+    }
+    lines
+}
+
+impl Profile {
+    /// Counts one executed instruction at `ic` that read memory at `reads`
+    /// and wrote to `write`.
+    pub fn record(
+        &mut self,
+        ic: usize,
+        inst: &Instruction,
+        reads: &[usize],
+        write: Option<usize>,
+    ) {
+        count(&mut self.opcodes, inst.opcode);
+        for mode in &inst.modes[..inst.opcode.n_params()] {
+            count(&mut self.modes, *mode);
+        }
+        count(&mut self.instructions, ic);
+        for address in reads {
+            count(&mut self.reads, *address);
+        }
+        if let Some(address) = write {
+            count(&mut self.writes, address);
+        }
+        match self.runs.last_mut() {
+            Some(run) => *run += 1,
+            None => self.runs.push(1),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.runs.iter().sum()
+    }
+
+    /// Sorted hot-spot report showing the `n` busiest rows of each table.
+    /// Instruction and memory addresses match the ones in `disasm` listings.
+    pub fn report(&self, n: usize) -> String {
+        let mut lines = vec![format!(
+            "{} instructions in {} run(s), at most {} in one run",
+            self.total(),
+            self.runs.len(),
+            self.runs.iter().max().unwrap_or(&0)
+        )];
+
+        let opcodes: HashMap<&str, u64> = self
+            .opcodes
+            .iter()
+            .map(|(opcode, count)| (opcode.mnemonic(), *count))
+            .collect();
+        let modes: HashMap<&str, u64> = self
+            .modes
+            .iter()
+            .map(|(mode, count)| {
+                let name = match mode {
+                    Mode::Position => "position",
+                    Mode::Immediate => "immediate",
+                    Mode::Relative => "relative",
+                };
+                (name, *count)
+            })
+            .collect();
+
+        for section in [
+            table("opcode", &opcodes, n),
+            table("mode", &modes, n),
+            table("ic", &self.instructions, n),
+            table("read", &self.reads, n),
+            table("write", &self.writes, n),
+        ] {
+            lines.push(String::new());
+            lines.extend(section);
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::computer::{run, ProgramState, StepResult};
+
+    #[test]
+    fn test_profile() {
+        // Counts [12] down from 3 and outputs it each time round
+        let program = [3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+        let mut state = ProgramState::new(&program);
+        state.profile = Some(Profile::default());
+        assert_eq!(run(&mut state), StepResult::NeedInput);
+        state.push_input(3);
+        while run(&mut state) != StepResult::Halted {}
+
+        let profile = state.profile.unwrap();
+        assert_eq!(profile.runs, vec![0, 2, 3, 3, 3]);
+        assert_eq!(profile.total(), 11);
+        assert_eq!(profile.opcodes[&Opcode::Output], 3);
+        assert_eq!(profile.opcodes[&Opcode::Halt], 1);
+        assert_eq!(profile.instructions[&8], 3);
+        assert_eq!(profile.modes[&Mode::Immediate], 6);
+        assert_eq!(profile.reads[&12], 9);
+        assert_eq!(profile.writes[&12], 4);
+
+        let report = profile.report(2);
+        assert!(report.starts_with("11 instructions in 5 run(s)"));
+        assert!(report.contains("\nADD                   3  27.27%\n"));
+        assert!(report.contains("\n12                    9 100.00%\n"));
+    }
+}
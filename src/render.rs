@@ -0,0 +1,295 @@
+extern crate ncurses;
+
+use std::io;
+use std::io::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+use crate::grid::{Grid, Point};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// Whatever the terminal draws text in.
+    Default,
+    Black,
+    Red,
+    Green,
+    Blue,
+    White,
+}
+
+impl Color {
+    const ALL: [Color; 5] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Blue,
+        Color::White,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+}
+
+/// Somewhere to draw a screen of text.  Points are cells counted from the
+/// top left, and anything drawn left of or above the screen is dropped.
+/// Nothing has to show until `present`.
+pub trait Renderer {
+    fn clear(&mut self);
+    fn draw_colored(&mut self, point: Point, text: &str, color: Color);
+
+    fn draw(&mut self, point: Point, text: &str) {
+        self.draw_colored(point, text, Color::Default);
+    }
+
+    /// Shows everything drawn so far as one frame.
+    fn present(&mut self);
+
+    /// Waits for the viewer to be done looking, if there's a viewer.
+    fn pause(&mut self) {}
+
+    /// A key pressed since the last call, without waiting for one.
+    fn poll_key(&mut self) -> Option<Key> {
+        None
+    }
+}
+
+/// Draws with ncurses, which takes over the terminal until this is dropped.
+/// Every frame is held for `delay` so animations can be followed.
+pub struct Ncurses {
+    delay: Duration,
+}
+
+impl Ncurses {
+    pub fn new(delay: Duration) -> Ncurses {
+        ncurses::initscr();
+        ncurses::start_color();
+        for (i, color) in Color::ALL.iter().enumerate() {
+            let fg = match color {
+                Color::Black => ncurses::COLOR_BLACK,
+                Color::Red => ncurses::COLOR_RED,
+                Color::Green => ncurses::COLOR_GREEN,
+                Color::Blue => ncurses::COLOR_BLUE,
+                _ => ncurses::COLOR_WHITE,
+            };
+            ncurses::init_pair(i as i16 + 1, fg, ncurses::COLOR_BLACK);
+        }
+        ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+        ncurses::noecho();
+        ncurses::keypad(ncurses::stdscr(), true);
+        Ncurses { delay }
+    }
+}
+
+impl Renderer for Ncurses {
+    fn clear(&mut self) {
+        ncurses::clear();
+    }
+
+    fn draw_colored(&mut self, point: Point, text: &str, color: Color) {
+        match Color::ALL.iter().position(|c| *c == color) {
+            Some(i) => {
+                let pair = ncurses::COLOR_PAIR(i as i16 + 1);
+                ncurses::attron(pair);
+                ncurses::mvprintw(point.y, point.x, text);
+                ncurses::attroff(pair);
+            }
+            None => {
+                ncurses::mvprintw(point.y, point.x, text);
+            }
+        }
+    }
+
+    fn present(&mut self) {
+        ncurses::refresh();
+        thread::sleep(self.delay);
+    }
+
+    fn pause(&mut self) {
+        ncurses::getch();
+    }
+
+    fn poll_key(&mut self) -> Option<Key> {
+        ncurses::nodelay(ncurses::stdscr(), true);
+        let c = ncurses::getch();
+        ncurses::nodelay(ncurses::stdscr(), false);
+        match c {
+            ncurses::KEY_UP => Some(Key::Up),
+            ncurses::KEY_DOWN => Some(Key::Down),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Ncurses {
+    fn drop(&mut self) {
+        ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_VISIBLE);
+        ncurses::endwin();
+    }
+}
+
+/// Draws with ANSI escape codes, so it works anywhere that passes them
+/// through, pipes included.  Every frame is held for `delay`.
+pub struct Ansi<W: Write> {
+    out: W,
+    delay: Duration,
+    // The lowest row drawn on, to leave the cursor below when done
+    bottom: i32,
+}
+
+impl Ansi<io::Stdout> {
+    pub fn stdout(delay: Duration) -> Ansi<io::Stdout> {
+        Ansi::new(io::stdout(), delay)
+    }
+}
+
+impl<W: Write> Ansi<W> {
+    pub fn new(mut out: W, delay: Duration) -> Ansi<W> {
+        // Drawing is best effort, so write errors are ignored here and below
+        let _ = write!(out, "\x1b[?25l");
+        Ansi {
+            out,
+            delay,
+            bottom: -1,
+        }
+    }
+}
+
+impl<W: Write> Renderer for Ansi<W> {
+    fn clear(&mut self) {
+        let _ = write!(self.out, "\x1b[2J");
+    }
+
+    fn draw_colored(&mut self, point: Point, text: &str, color: Color) {
+        if point.x < 0 || point.y < 0 {
+            return;
+        }
+        self.bottom = self.bottom.max(point.y);
+        let code = match color {
+            Color::Default => 39,
+            Color::Black => 30,
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Blue => 34,
+            Color::White => 37,
+        };
+        let _ = write!(
+            self.out,
+            "\x1b[{};{}H\x1b[{}m{}\x1b[0m",
+            point.y + 1,
+            point.x + 1,
+            code,
+            text
+        );
+    }
+
+    fn present(&mut self) {
+        let _ = self.out.flush();
+        thread::sleep(self.delay);
+    }
+}
+
+impl<W: Write> Drop for Ansi<W> {
+    fn drop(&mut self) {
+        let _ = write!(self.out, "\x1b[{};1H\x1b[?25h", self.bottom + 2);
+        let _ = self.out.flush();
+    }
+}
+
+/// Draws into memory as plain text, keeping a copy of every frame presented,
+/// so tests can check what a renderer would have shown.  Colors are
+/// dropped.
+pub struct FrameBuffer {
+    screen: Grid<char>,
+    pub frames: Vec<String>,
+}
+
+impl FrameBuffer {
+    pub fn new() -> FrameBuffer {
+        FrameBuffer {
+            screen: Grid::sparse(),
+            frames: vec![],
+        }
+    }
+
+    /// The screen as it stands, one line per row with trailing blanks
+    /// trimmed.
+    pub fn frame(&self) -> String {
+        let bounds = match self.screen.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+        let mut lines = vec![];
+        for y in 0..=bounds.max.y {
+            let line: String = (0..=bounds.max.x)
+                .map(|x| *self.screen.get(Point::new(x, y)).unwrap_or(&' '))
+                .collect();
+            lines.push(line.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+impl Default for FrameBuffer {
+    fn default() -> FrameBuffer {
+        FrameBuffer::new()
+    }
+}
+
+impl Renderer for FrameBuffer {
+    fn clear(&mut self) {
+        self.screen = Grid::sparse();
+    }
+
+    fn draw_colored(&mut self, point: Point, text: &str, _color: Color) {
+        if point.x < 0 || point.y < 0 {
+            return;
+        }
+        for (i, c) in text.chars().enumerate() {
+            let cell = Point::new(point.x + i as i32, point.y);
+            self.screen.insert(cell, c);
+        }
+    }
+
+    fn present(&mut self) {
+        self.frames.push(self.frame());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frame_buffer() {
+        let mut screen = FrameBuffer::new();
+        screen.draw(Point::new(1, 0), "ab");
+        screen.draw_colored(Point::new(0, 2), "c", Color::Red);
+        screen.draw(Point::new(-1, 1), "off screen");
+        screen.present();
+        assert_eq!(screen.frame(), " ab\n\nc");
+
+        screen.clear();
+        screen.draw(Point::new(0, 0), "d");
+        screen.present();
+        assert_eq!(screen.frames, vec![" ab\n\nc", "d"]);
+    }
+
+    #[test]
+    fn test_ansi() {
+        let mut out = vec![];
+        {
+            let mut screen = Ansi::new(&mut out, Duration::from_millis(0));
+            screen.clear();
+            screen.draw_colored(Point::new(2, 1), "#", Color::Blue);
+            screen.present();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b[?25l\x1b[2J\x1b[2;3H\x1b[34m#\x1b[0m\x1b[3;1H\x1b[?25h"
+        );
+    }
+}
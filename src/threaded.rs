@@ -0,0 +1,104 @@
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+use std::thread::JoinHandle;
+
+use crate::computer::{try_run, IntcodeError, ProgramState, StepResult};
+
+/// A `ProgramState` running on its own thread.  Input is read from a channel
+/// once the state's own queue runs dry, and every output is sent to each of
+/// the output channels, so machines can be wired into pipelines, rings or
+/// fan-outs.
+///
+/// A machine stops when it halts, or when it needs input and every sender
+/// for its input channel is gone.  Either way it drops its output senders,
+/// so halting one machine winds down everything downstream of it.  Sends to
+/// a receiver that has already gone away are ignored.
+pub struct ThreadedMachine {
+    handle: JoinHandle<Result<ProgramState, IntcodeError>>,
+}
+
+impl ThreadedMachine {
+    pub fn spawn(
+        mut state: ProgramState,
+        input: Receiver<i64>,
+        outputs: Vec<Sender<i64>>,
+    ) -> ThreadedMachine {
+        let handle = thread::spawn(move || loop {
+            match try_run(&mut state)? {
+                StepResult::Output(x) => {
+                    for output in &outputs {
+                        let _ = output.send(x);
+                    }
+                }
+                StepResult::NeedInput => match input.recv() {
+                    Ok(x) => state.push_input(x),
+                    Err(_) => return Ok(state),
+                },
+                StepResult::Halted | StepResult::BudgetExceeded => {
+                    return Ok(state)
+                }
+            }
+        });
+        ThreadedMachine { handle }
+    }
+
+    /// Waits for the machine to stop and hands back its final state.
+    pub fn join(self) -> Result<ProgramState, IntcodeError> {
+        self.handle.join().expect("Intcode machine thread panicked")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::computer::ProgramStatus;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_pipeline() {
+        // Doubles every input forever
+        let doubler = [3, 11, 102, 2, 11, 11, 4, 11, 1105, 1, 0, 0];
+        let (first, input) = channel();
+        let (middle, middle_input) = channel();
+        let (last, results) = channel();
+        let (tap, tapped) = channel();
+
+        let a = ThreadedMachine::spawn(
+            ProgramState::new(&doubler),
+            input,
+            vec![middle],
+        );
+        let b = ThreadedMachine::spawn(
+            ProgramState::new(&doubler),
+            middle_input,
+            vec![last, tap],
+        );
+        for x in 1..=3 {
+            first.send(x).unwrap();
+        }
+        drop(first);
+
+        // Closing the first input shuts the whole pipeline down
+        let a = a.join().unwrap();
+        let b = b.join().unwrap();
+        assert_eq!(a.status, ProgramStatus::WaitingForInput);
+        assert_eq!(b.status, ProgramStatus::WaitingForInput);
+        assert_eq!(results.iter().collect::<Vec<_>>(), vec![4, 8, 12]);
+        assert_eq!(tapped.iter().collect::<Vec<_>>(), vec![4, 8, 12]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (_, input) = channel();
+        let (output, _) = channel();
+        let machine = ThreadedMachine::spawn(
+            ProgramState::new(&[42]),
+            input,
+            vec![output],
+        );
+        assert_eq!(
+            machine.join().unwrap_err(),
+            IntcodeError::UnknownOpcode { ic: 0, opcode: 42 }
+        );
+    }
+}
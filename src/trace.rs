@@ -0,0 +1,221 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use crate::computer::Opcode;
+
+const MAGIC: &[u8; 4] = b"ICT1";
+
+/// One executed instruction.  `operands` holds the resolved value of every
+/// parameter the instruction reads, so a destination address shows up in
+/// `write` instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub ic: usize,
+    pub opcode: Opcode,
+    pub operands: Vec<i64>,
+    pub write: Option<(usize, i64)>,
+    pub relative_base: Option<i64>,
+}
+
+impl TraceEntry {
+    fn n_operands(opcode: Opcode) -> usize {
+        opcode.n_params() - opcode.writes() as usize
+    }
+}
+
+/// Instructions recorded by `computer::step` while `ProgramState::trace` is
+/// set, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+}
+
+// Signed values are zigzag encoded so small negatives stay small, then
+// written as LEB128 varints
+fn write_varint<W: Write>(w: &mut W, value: i64) -> io::Result<()> {
+    let mut x = ((value << 1) ^ (value >> 63)) as u64;
+    loop {
+        let byte = (x & 0x7f) as u8;
+        x >>= 7;
+        if x == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(r: &mut R) -> io::Result<i64> {
+    let mut x = 0u64;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        x |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok((x >> 1) as i64 ^ -((x & 1) as i64));
+        }
+        shift += 7;
+        if shift >= 64 {
+            return Err(bad_trace("varint too long"));
+        }
+    }
+}
+
+fn bad_trace(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Trace {
+    /// Writes the trace in its compact binary form: a magic number followed
+    /// by one record per entry of varint `ic`, opcode, a flags byte saying
+    /// whether a write and a relative base change follow, then the operands
+    /// and those optional fields.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        for entry in &self.entries {
+            write_varint(w, entry.ic as i64)?;
+            write_varint(w, entry.opcode.code())?;
+            let flags = entry.write.is_some() as u8
+                | (entry.relative_base.is_some() as u8) << 1;
+            w.write_all(&[flags])?;
+            for operand in &entry.operands {
+                write_varint(w, *operand)?;
+            }
+            if let Some((address, value)) = entry.write {
+                write_varint(w, address as i64)?;
+                write_varint(w, value)?;
+            }
+            if let Some(relative_base) = entry.relative_base {
+                write_varint(w, relative_base)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Trace> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(bad_trace("not an Intcode trace"));
+        }
+
+        let mut entries = vec![];
+        loop {
+            let ic = match read_varint(r) {
+                Ok(ic) => ic as usize,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            };
+            let opcode = Opcode::from_code(read_varint(r)?)
+                .ok_or_else(|| bad_trace("bad opcode"))?;
+            let mut flags = [0];
+            r.read_exact(&mut flags)?;
+
+            let operands = (0..TraceEntry::n_operands(opcode))
+                .map(|_| read_varint(r))
+                .collect::<io::Result<_>>()?;
+            let write = if flags[0] & 1 != 0 {
+                Some((read_varint(r)? as usize, read_varint(r)?))
+            } else {
+                None
+            };
+            let relative_base = if flags[0] & 2 != 0 {
+                Some(read_varint(r)?)
+            } else {
+                None
+            };
+            entries.push(TraceEntry {
+                ic,
+                opcode,
+                operands,
+                write,
+                relative_base,
+            });
+        }
+        Ok(Trace { entries })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut f = io::BufWriter::new(File::create(path)?);
+        self.write_to(&mut f)?;
+        f.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Trace> {
+        let mut f = io::BufReader::new(File::open(path)?);
+        Trace::read_from(&mut f)
+    }
+
+    /// Human readable form, one instruction a line, e.g.
+    /// `    12  ADD 3, -1  [40] <- 2`.  Two dumps can be diffed to find where
+    /// runs diverge.
+    pub fn dump(&self) -> String {
+        let mut text = String::new();
+        for entry in &self.entries {
+            let mut parts = vec![
+                format!("{:>6}", entry.ic),
+                entry.opcode.mnemonic().to_string(),
+            ];
+            if !entry.operands.is_empty() {
+                let operands: Vec<String> =
+                    entry.operands.iter().map(|x| x.to_string()).collect();
+                parts[1] = format!("{:<3} {}", parts[1], operands.join(", "));
+            }
+            if let Some((address, value)) = entry.write {
+                parts.push(format!("[{}] <- {}", address, value));
+            }
+            if let Some(relative_base) = entry.relative_base {
+                parts.push(format!("rb <- {}", relative_base));
+            }
+            text += &parts.join("  ");
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::computer::{ProgramState, StepResult};
+
+    #[test]
+    fn test_trace() {
+        let mut state = ProgramState::new(&[109, -4, 203, 7, 4, 3, 99]);
+        state.trace = Some(Trace::default());
+        state.push_input(-300);
+        assert_eq!(state.run_until_blocked(), StepResult::Halted);
+
+        let trace = state.trace.take().unwrap();
+        assert_eq!(
+            trace.dump(),
+            [
+                "     0  ARB -4  rb <- -4",
+                "     2  IN  [3] <- -300",
+                "     4  OUT -300",
+                "     6  HLT",
+            ]
+            .join("\n")
+                + "\n"
+        );
+
+        let mut bytes = vec![];
+        trace.write_to(&mut bytes).unwrap();
+        assert_eq!(Trace::read_from(&mut &bytes[..]).unwrap(), trace);
+        assert!(Trace::read_from(&mut &b"nope"[..]).is_err());
+    }
+
+    #[test]
+    fn test_varint() {
+        for x in &[0, 1, -1, 63, -64, 64, 1 << 40, i64::MAX, i64::MIN] {
+            let mut bytes = vec![];
+            write_varint(&mut bytes, *x).unwrap();
+            assert_eq!(read_varint(&mut &bytes[..]).unwrap(), *x);
+        }
+        let mut bytes = vec![];
+        write_varint(&mut bytes, -3).unwrap();
+        assert_eq!(bytes, vec![5]);
+    }
+}